@@ -24,7 +24,7 @@ export function hslaToRgbaFlatArray(hslaBuffer) {
     const l = hslaBuffer[i + 2] / 255;   // Lightness [0,1]
     const a = hslaBuffer[i + 3];         // Alpha remains 0–255
 
-    const {r, g, b} = rgbaToHsla(h, s, l, 0);
+    const {r, g, b} = hslToRgb(h, s, l);
 
     rgbaBuffer[i] = r;
     rgbaBuffer[i + 1] = g;
@@ -35,6 +35,28 @@ export function hslaToRgbaFlatArray(hslaBuffer) {
   return rgbaBuffer;
 }
 
+// h, s and l in [0,1]; returns r, g and b in 0–255, as hsl_to_rgb in the wasm crate
+export function hslToRgb(h, s, l) {
+  if (s === 0) {
+    const v = Math.round(l * 255);
+    return { r: v, g: v, b: v };
+  }
+  const f2 = l < 0.5 ? l * (1 + s) : l + s - s * l;
+  const f1 = 2 * l - f2;
+  const channel = (t) => {
+    t = ((t % 1) + 1) % 1;
+    if (6 * t < 1) return f1 + (f2 - f1) * 6 * t;
+    if (2 * t < 1) return f2;
+    if (3 * t < 2) return f1 + (f2 - f1) * (2 / 3 - t) * 6;
+    return f1;
+  };
+  return {
+    r: Math.round(channel(h + 1 / 3) * 255),
+    g: Math.round(channel(h) * 255),
+    b: Math.round(channel(h - 1 / 3) * 255),
+  };
+}
+
 export function newId() {
   const high = BigInt(Math.floor(Math.random() * 0xFFFFFFFF));
   const low = BigInt(Math.floor(Math.random() * 0xFFFFFFFF));
@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+
+use crate::{Individual, LifeChannel};
+
+/// Default number of generations the activity average spans.
+pub(crate) const DEFAULT_ACTIVITY_WINDOW: u32 = 32;
+
+/// An exponential moving average, per cell, of how often it is born or
+/// dies: 0 for settled ash, towards 1 for cells flipping every generation.
+///
+/// Decay is lazy: each value is stored as of the last generation its cell
+/// flipped, so a generation only visits the regions that changed.  `settle`
+/// brings every value up to date before `values` is read.
+#[derive(Clone)]
+pub(crate) struct ActivityLayer {
+    pub enabled: bool,
+    pub values: Vec<f32>,
+    /// Generations the average roughly spans; each update weighs the new
+    /// event by `1 / window`.
+    pub window: u32,
+    /// Update at which each value was last brought up to date.
+    updated: Vec<u64>,
+    /// Updates folded in since the last reset.
+    clock: u64,
+    /// Whether every value is up to date.
+    settled: bool,
+}
+
+impl Default for ActivityLayer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            values: Vec::new(),
+            window: DEFAULT_ACTIVITY_WINDOW,
+            updated: Vec::new(),
+            clock: 0,
+            settled: true,
+        }
+    }
+}
+
+impl ActivityLayer {
+    /// Zero every cell, or free the buffer while disabled.
+    pub fn reset(&mut self, len: usize) {
+        self.values.clear();
+        self.updated.clear();
+        self.clock = 0;
+        self.settled = true;
+        if self.enabled {
+            self.values.resize(len, 0.0);
+            self.updated.resize(len, 0);
+        }
+    }
+
+    /// Fold in one generation that went from `previous` to `cells`, changing
+    /// only cells within `regions` (`(x0, y0, x1, y1)` bounds).
+    pub fn update(
+        &mut self,
+        cells: &[Individual],
+        previous: &[Individual],
+        width: u32,
+        regions: impl Iterator<Item = (u32, u32, u32, u32)>,
+        channel: LifeChannel,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        if self.values.len() != cells.len() {
+            self.reset(cells.len());
+        }
+        self.clock += 1;
+        self.settled = false;
+        let weight = 1.0 / self.window as f32;
+        for (x0, y0, x1, y1) in regions {
+            for row in y0..y1 {
+                for idx in (row * width + x0) as usize..(row * width + x1) as usize {
+                    let (new, old) = (cells[idx], previous[idx]);
+                    if (new.activity_value(channel) > 0) != (old.activity_value(channel) > 0) {
+                        // Decay through this generation, then count the flip
+                        self.values[idx] = self.value(idx) + weight;
+                        self.updated[idx] = self.clock;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Bring every value up to date, e.g. before changing the window or
+    /// handing out `values`.
+    pub fn settle(&mut self) {
+        if self.settled {
+            return;
+        }
+        for idx in 0..self.values.len() {
+            self.values[idx] = self.value(idx);
+            self.updated[idx] = self.clock;
+        }
+        self.settled = true;
+    }
+
+    /// Every value up to date, without settling.
+    pub fn current(&self) -> Cow<'_, [f32]> {
+        if self.settled {
+            Cow::Borrowed(&self.values)
+        } else {
+            Cow::Owned((0..self.values.len()).map(|idx| self.value(idx)).collect())
+        }
+    }
+
+    /// Activity saved with `save_state`, little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.current().iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// Mean activity of the cells in columns `x0..x1` of rows `y0..y1`.
+    pub fn region_mean(&self, width: u32, (x0, y0, x1, y1): (u32, u32, u32, u32)) -> f32 {
+        if self.values.is_empty() || x1 <= x0 || y1 <= y0 {
+            return 0.0;
+        }
+        let mut sum = 0.0f64;
+        for row in y0..y1 {
+            let start = (row * width + x0) as usize;
+            sum += (start..start + (x1 - x0) as usize).map(|idx| self.value(idx) as f64).sum::<f64>();
+        }
+        (sum / ((x1 - x0) as f64 * (y1 - y0) as f64)) as f32
+    }
+
+    /// The value of `idx` now, decayed over the updates since it was
+    /// stored.
+    #[inline]
+    fn value(&self, idx: usize) -> f32 {
+        let value = self.values[idx];
+        match self.clock - self.updated[idx] {
+            0 => value,
+            age => value * (1.0 - 1.0 / self.window as f32).powi(age.min(i32::MAX as u64) as i32),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{Individual, LifeChannel};
+
+/// Generations each cell has spent in its current state: alive since its
+/// birth, dead since its death (so ghosts age too).  Kept off the cells so
+/// it costs nothing while disabled.
+#[derive(Clone, Default)]
+pub(crate) struct AgeLayer {
+    pub enabled: bool,
+    pub ages: Vec<u32>,
+    /// Whether each cell was alive at the last update; any change, from a
+    /// tick or an edit in between, restarts its age.
+    alive: Vec<bool>,
+    /// Mean and maximum age of the live cells at the last update.
+    pub mean: f32,
+    pub max: u32,
+}
+
+impl AgeLayer {
+    /// Start every cell at age 0 in its current state, or free the buffers
+    /// while disabled.
+    pub fn reset(&mut self, cells: &[Individual], channel: LifeChannel) {
+        self.ages.clear();
+        self.alive.clear();
+        self.mean = 0.0;
+        self.max = 0;
+        if self.enabled {
+            self.ages.resize(cells.len(), 0);
+            self.alive.extend(cells.iter().map(|c| c.activity_value(channel) > 0));
+        }
+    }
+
+    /// Age every cell by one generation.
+    pub fn update(&mut self, cells: &[Individual], channel: LifeChannel) {
+        if !self.enabled {
+            return;
+        }
+        if self.ages.len() != cells.len() {
+            self.reset(cells, channel);
+            return;
+        }
+        let (mut sum, mut count, mut max) = (0u64, 0u64, 0u32);
+        for ((age, was_alive), cell) in self.ages.iter_mut().zip(&mut self.alive).zip(cells) {
+            let alive = cell.activity_value(channel) > 0;
+            *age = if alive == *was_alive { age.saturating_add(1) } else { 0 };
+            *was_alive = alive;
+            if alive {
+                sum += *age as u64;
+                count += 1;
+                max = max.max(*age);
+            }
+        }
+        self.mean = if count > 0 { sum as f32 / count as f32 } else { 0.0 };
+        self.max = max;
+    }
+
+    /// Ages saved with `save_state`, little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.ages.iter().flat_map(|age| age.to_le_bytes()).collect()
+    }
+
+    /// Restore saved ages for `cells`, ignoring data of the wrong size.
+    pub fn load_bytes(&mut self, bytes: &[u8], cells: &[Individual], channel: LifeChannel) {
+        self.reset(cells, channel);
+        if self.enabled && bytes.len() == cells.len() * 4 {
+            for (age, chunk) in self.ages.iter_mut().zip(bytes.chunks_exact(4)) {
+                *age = u32::from_le_bytes(chunk.try_into().unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Animated GIF and APNG export of a run, for `Universe::export_animation`.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AnimationFormat {
+    /// 256 colours per frame, quantised with NeuQuant; alpha is on or off.
+    Gif,
+    /// Lossless, with full alpha.
+    Apng,
+}
+
+/// What `Universe::export_animation` records and how it encodes it.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    /// Generations to run after the first frame.
+    pub generations: u32,
+    /// Generations between frames; the last generation is always a frame.
+    pub every: u32,
+    /// Region to record; a zero width or height means the whole grid.
+    pub crop_x: u32,
+    pub crop_y: u32,
+    pub crop_width: u32,
+    pub crop_height: u32,
+    /// Output size relative to the region, by nearest pixel.
+    pub scale: f32,
+    /// Time each frame is shown, in milliseconds.
+    pub delay_ms: u32,
+    /// Play forever rather than once.
+    pub repeat: bool,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::Gif,
+            generations: 100,
+            every: 1,
+            crop_x: 0,
+            crop_y: 0,
+            crop_width: 0,
+            crop_height: 0,
+            scale: 1.0,
+            delay_ms: 100,
+            repeat: true,
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl AnimationOptions {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(format: AnimationFormat, generations: u32, every: u32) -> AnimationOptions {
+        AnimationOptions { format, generations, every, ..AnimationOptions::default() }
+    }
+
+    /// Record only the `width` x `height` region at `(x, y)`.
+    pub fn set_crop(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        (self.crop_x, self.crop_y, self.crop_width, self.crop_height) = (x, y, width, height);
+    }
+}
+
+impl AnimationOptions {
+    /// Number of frames, counting the starting state.
+    pub(crate) fn frame_count(&self) -> u32 {
+        1 + self.generations.div_ceil(self.every.max(1))
+    }
+
+    /// The recorded region `(x, y, width, height)` within a grid.
+    pub(crate) fn region(&self, width: u32, height: u32) -> Result<(u32, u32, u32, u32), String> {
+        if self.crop_width == 0 || self.crop_height == 0 {
+            return Ok((0, 0, width, height));
+        }
+        let fits = |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|end| end <= size);
+        if !fits(self.crop_x, self.crop_width, width) || !fits(self.crop_y, self.crop_height, height) {
+            return Err(format!(
+                "crop {}x{} at ({}, {}) exceeds the {}x{} grid",
+                self.crop_width, self.crop_height, self.crop_x, self.crop_y, width, height
+            ));
+        }
+        Ok((self.crop_x, self.crop_y, self.crop_width, self.crop_height))
+    }
+
+    /// Encoded frame size for a region.
+    pub(crate) fn output_size(&self, width: u32, height: u32) -> Result<(u32, u32), String> {
+        if !(self.scale > 0.0 && self.scale.is_finite()) {
+            return Err(format!("invalid animation scale {}", self.scale));
+        }
+        let scaled = |len: u32| ((len as f32 * self.scale).round() as u32).max(1);
+        let (w, h) = (scaled(width), scaled(height));
+        if self.format == AnimationFormat::Gif && (w > u16::MAX as u32 || h > u16::MAX as u32) {
+            return Err(format!("{}x{} is too large for a GIF", w, h));
+        }
+        Ok((w, h))
+    }
+}
+
+/// Encode `count` frames of `width` x `height` RGBA pulled from
+/// `next_frame`, one at a time so long runs aren't held in memory.
+pub(crate) fn encode(
+    options: &AnimationOptions,
+    width: u32,
+    height: u32,
+    count: u32,
+    mut next_frame: impl FnMut() -> Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    match options.format {
+        AnimationFormat::Gif => {
+            let err = |e: gif::EncodingError| format!("cannot encode GIF: {}", e);
+            let mut encoder = gif::Encoder::new(Vec::new(), width as u16, height as u16, &[]).map_err(err)?;
+            if options.repeat {
+                encoder.set_repeat(gif::Repeat::Infinite).map_err(err)?;
+            }
+            let delay = (options.delay_ms / 10).min(u16::MAX as u32) as u16;
+            for _ in 0..count {
+                let mut pixels = next_frame();
+                let mut frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut pixels, 10);
+                frame.delay = delay;
+                encoder.write_frame(&frame).map_err(err)?;
+            }
+            encoder.into_inner().map_err(|e| format!("cannot encode GIF: {}", e))
+        }
+        AnimationFormat::Apng => {
+            let err = |e: png::EncodingError| format!("cannot encode APNG: {}", e);
+            let mut out = Vec::new();
+            let mut encoder = png::Encoder::new(&mut out, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(count, if options.repeat { 0 } else { 1 }).map_err(err)?;
+            encoder.set_frame_delay(options.delay_ms.min(u16::MAX as u32) as u16, 1000).map_err(err)?;
+            let mut writer = encoder.write_header().map_err(err)?;
+            for _ in 0..count {
+                writer.write_image_data(&next_frame()).map_err(err)?;
+            }
+            writer.finish().map_err(err)?;
+            Ok(out)
+        }
+    }
+}
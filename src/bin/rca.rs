@@ -0,0 +1,532 @@
+//! Headless command-line runner: builds a `Universe`, runs it for a number
+//! of generations and writes PNG frames, statistics and the final state.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use ca::{
+    parse_rule, preset, AnimationFormat, AnimationOptions, Dither, ImportOptions, LifeChannel, LifeParams, Palette, Placement, Preset, Resample, RgbaImage, TrailMode,
+    Universe, UniverseStats, PALETTE_NAMES, PRESETS,
+};
+
+const USAGE: &str = "\
+Usage: rca [options]
+
+Universe:
+  --size WxH             grid size (default 256x256)
+  --rule RULE            birth/survival rule, e.g. B3/S23 (overrides --params)
+  --params FILE          JSON file with LifeParams fields
+  --preset NAME          start from a built-in preset's params and seed
+                         (--params, --rule and --seed override it)
+  --list-presets         print the built-in presets and exit
+  --seed N               RNG seed for randomize and hue drift
+
+Initial state (applied in this order):
+  --random               fill the grid with random cells
+  --state FILE           load a file written by save_state / --final-state; its
+                         size, params, seed and generation replace the above
+                         except an explicit --params or --rule
+  --load-png FILE        replace the grid with a PNG, scaled to fit; raw PNGs
+                         from --final-raw restore the cells exactly
+  --image FILE           stamp a PNG into the centre of the grid
+  --import FILE          scale a PNG or photo onto the grid
+  --import-placement P   fit, fill, stretch or tile (default fit)
+  --import-resample R    nearest, bilinear or area (default area)
+  --import-dither D      none, floyd-steinberg or ordered: seed live cells
+                         from brightness (default none)
+
+Run:
+  --generations N        generations to run (default 100)
+  --frames DIR           write frame_<generation>.png files into DIR
+  --frame-every K        generations between frames (default 10)
+  --alpha                keep cell alpha in frames instead of rendering opaque
+  --palette NAME|FILE    colour frames through a palette: a built-in name
+                         (see --list-palettes) or a JSON palette file
+  --palette-channel CH   channel indexing the palette: hue, saturation,
+                         luminance or alpha (default alpha)
+  --list-palettes        print the built-in palette names and exit
+  --stats FILE           write statistics; .json for JSON, anything else CSV
+  --stats-every K        generations between statistics rows (default 1)
+  --animation FILE       write the run as an animated .gif, or .png (APNG)
+  --animation-every K    generations between animation frames (default 1)
+  --animation-crop X,Y,W,H
+                         animate only this region of the grid
+  --animation-scale S    scale animation frames by S (default 1)
+  --animation-delay MS   time each animation frame is shown (default 100)
+  --trail FILE           write a long-exposure PNG of every generation run
+  --trail-mode MODE[:P]  blend generations by additive, max or average, keeping
+                         P (0-1) of the trail each time (default max:1)
+  --trail-exposure E     brightness of the trail image (default 1)
+  --final-state FILE     save the final state
+  --final-png FILE       save the final grid as rendered
+  --final-raw FILE       save the final cells losslessly as a raw HSLA PNG
+  -h, --help             show this help
+";
+
+#[derive(Debug)]
+struct Options {
+    width: u32,
+    height: u32,
+    rule: Option<u32>,
+    params: Option<PathBuf>,
+    preset: Option<&'static Preset>,
+    list_presets: bool,
+    seed: Option<u64>,
+    random: bool,
+    state: Option<PathBuf>,
+    load_png: Option<PathBuf>,
+    image: Option<PathBuf>,
+    import: Option<PathBuf>,
+    import_options: ImportOptions,
+    generations: u64,
+    frames: Option<PathBuf>,
+    frame_every: u64,
+    alpha: bool,
+    palette: Option<String>,
+    palette_channel: LifeChannel,
+    list_palettes: bool,
+    stats: Option<PathBuf>,
+    stats_every: u64,
+    animation: Option<PathBuf>,
+    animation_options: AnimationOptions,
+    trail: Option<PathBuf>,
+    trail_mode: (TrailMode, f32),
+    trail_exposure: f32,
+    final_state: Option<PathBuf>,
+    final_png: Option<PathBuf>,
+    final_raw: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            rule: None,
+            params: None,
+            preset: None,
+            list_presets: false,
+            seed: None,
+            random: false,
+            state: None,
+            load_png: None,
+            image: None,
+            import: None,
+            import_options: ImportOptions::default(),
+            generations: 100,
+            frames: None,
+            frame_every: 10,
+            alpha: false,
+            palette: None,
+            palette_channel: LifeChannel::Alpha,
+            list_palettes: false,
+            stats: None,
+            stats_every: 1,
+            animation: None,
+            animation_options: AnimationOptions::default(),
+            trail: None,
+            trail_mode: (TrailMode::Max, 1.0),
+            trail_exposure: 1.0,
+            final_state: None,
+            final_png: None,
+            final_raw: None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match parse_args(&args).and_then(|options| run(&options)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rca: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} expects a value", arg))
+        };
+        let number = |text: String| {
+            text.parse::<u64>()
+                .map_err(|_| format!("{} expects a number, got '{}'", arg, text))
+        };
+
+        match arg.as_str() {
+            "--size" => {
+                let text = value()?;
+                let (w, h) = text
+                    .split_once(['x', 'X'])
+                    .ok_or_else(|| format!("--size expects WxH, got '{}'", text))?;
+                let side = |text: &str| {
+                    u32::try_from(number(text.to_string())?).map_err(|_| format!("--size {} is too large", text))
+                };
+                options.width = side(w)?;
+                options.height = side(h)?;
+            }
+            "--rule" => options.rule = Some(parse_rule(&value()?)?),
+            "--params" => options.params = Some(value()?.into()),
+            "--preset" => {
+                let name = value()?;
+                options.preset = Some(preset(&name).ok_or_else(|| format!("unknown preset '{}'", name))?);
+            }
+            "--list-presets" => options.list_presets = true,
+            "--seed" => options.seed = Some(number(value()?)?),
+            "--random" => options.random = true,
+            "--state" => options.state = Some(value()?.into()),
+            "--load-png" => options.load_png = Some(value()?.into()),
+            "--image" => options.image = Some(value()?.into()),
+            "--import" => options.import = Some(value()?.into()),
+            "--import-placement" => options.import_options.placement = parse_placement(&value()?)?,
+            "--import-resample" => options.import_options.resample = parse_resample(&value()?)?,
+            "--import-dither" => options.import_options.dither = parse_dither(&value()?)?,
+            "--generations" => options.generations = number(value()?)?,
+            "--frames" => options.frames = Some(value()?.into()),
+            "--frame-every" => options.frame_every = number(value()?)?.max(1),
+            "--alpha" => options.alpha = true,
+            "--palette" => options.palette = Some(value()?),
+            "--palette-channel" => options.palette_channel = parse_channel(&value()?)?,
+            "--list-palettes" => options.list_palettes = true,
+            "--stats" => options.stats = Some(value()?.into()),
+            "--stats-every" => options.stats_every = number(value()?)?.max(1),
+            "--animation" => options.animation = Some(value()?.into()),
+            "--animation-every" => {
+                options.animation_options.every = u32::try_from(number(value()?)?).unwrap_or(u32::MAX).max(1);
+            }
+            "--animation-crop" => {
+                let text = value()?;
+                let parts: Vec<u32> = text.split(',').map(|part| part.trim().parse()).collect::<Result<_, _>>()
+                    .map_err(|_| format!("--animation-crop expects X,Y,W,H, got '{}'", text))?;
+                let [x, y, w, h] = parts[..] else {
+                    return Err(format!("--animation-crop expects X,Y,W,H, got '{}'", text));
+                };
+                options.animation_options.set_crop(x, y, w, h);
+            }
+            "--animation-scale" => {
+                let text = value()?;
+                options.animation_options.scale = text.parse().map_err(|_| format!("invalid scale '{}'", text))?;
+            }
+            "--animation-delay" => {
+                options.animation_options.delay_ms = u32::try_from(number(value()?)?).unwrap_or(u32::MAX);
+            }
+            "--trail" => options.trail = Some(value()?.into()),
+            "--trail-mode" => options.trail_mode = parse_trail_mode(&value()?)?,
+            "--trail-exposure" => {
+                let text = value()?;
+                options.trail_exposure = text.parse().map_err(|_| format!("invalid exposure '{}'", text))?;
+            }
+            "--final-state" => options.final_state = Some(value()?.into()),
+            "--final-png" => options.final_png = Some(value()?.into()),
+            "--final-raw" => options.final_raw = Some(value()?.into()),
+            other => return Err(format!("unknown option '{}'\n\n{}", other, USAGE)),
+        }
+    }
+
+    if options.width == 0 || options.height == 0 {
+        return Err("--size must be at least 1x1".to_string());
+    }
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    if options.list_presets {
+        for preset in PRESETS {
+            println!("{:<16}{}", preset.name, preset.description);
+        }
+        return Ok(());
+    }
+    if options.list_palettes {
+        for name in PALETTE_NAMES {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+
+    let mut params = match (&options.params, options.preset) {
+        (Some(path), _) => load_params(path)?,
+        (None, Some(preset)) => preset.params,
+        (None, None) => LifeParams::default(),
+    };
+    if let Some(rule) = options.rule {
+        params.rule = rule;
+    }
+
+    let mut universe = Universe::with_params(options.width, options.height, params);
+    if let Some(seed) = options.seed.or(options.preset.map(|preset| preset.seed)) {
+        universe.set_seed(seed);
+    }
+    if options.random {
+        universe.randomize();
+    }
+    if let Some(path) = &options.state {
+        let state = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        universe.load_state(&state).map_err(|e| format!("{}: {}", path.display(), e))?;
+        // Explicit --params / --rule win over those saved with the state
+        if options.params.is_some() || options.rule.is_some() {
+            universe.set_params(
+                params.rule,
+                params.decay_step,
+                params.recovery_step,
+                params.sat_recovery_factor,
+                params.sat_decay_factor,
+                params.lum_decay_factor,
+                params.life_decay_factor,
+                params.sat_ghost_factor,
+                params.hue_drift_strength,
+                params.hue_lerp_factor,
+                params.life_channel,
+            );
+        }
+    }
+    if let Some(path) = &options.load_png {
+        let png = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        universe.import_png(&png).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &options.image {
+        stamp_image(&mut universe, path)?;
+    }
+    if let Some(path) = &options.import {
+        let png = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        universe.import_image(&png, &options.import_options).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    universe.set_display_mode(options.alpha, false);
+    if let Some(palette) = &options.palette {
+        load_palette(&mut universe, palette)?;
+        universe.set_palette_channel(options.palette_channel);
+    }
+
+    // The animation runs on a copy, leaving the universe for the run below
+    if let Some(path) = &options.animation {
+        let gif = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+        let animation = AnimationOptions {
+            format: if gif { AnimationFormat::Gif } else { AnimationFormat::Apng },
+            generations: u32::try_from(options.generations).map_err(|_| "too many generations to animate")?,
+            ..options.animation_options
+        };
+        write_file(path, universe.export_animation(&animation))?;
+    }
+
+    if options.trail.is_some() {
+        universe.set_trail_enabled(true);
+        universe.set_trail_mode(options.trail_mode.0, options.trail_mode.1);
+        universe.set_trail_exposure(options.trail_exposure);
+    }
+
+    if let Some(dir) = &options.frames {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        write_frame(&mut universe, dir)?;
+    }
+
+    let mut rows = Vec::new();
+    let mut generation = 0u64;
+    while generation < options.generations {
+        // Run straight to the next generation anything is recorded at
+        let mut next = options.generations;
+        if options.frames.is_some() {
+            next = next.min((generation / options.frame_every + 1).saturating_mul(options.frame_every));
+        }
+        if options.stats.is_some() {
+            next = next.min((generation / options.stats_every + 1).saturating_mul(options.stats_every));
+        }
+
+        let mut remaining = next - generation;
+        while remaining > 0 {
+            let chunk = remaining.min(u32::MAX as u64);
+            universe.tick_n(chunk as u32);
+            remaining -= chunk;
+        }
+        generation = next;
+
+        if let Some(dir) = &options.frames {
+            if generation.is_multiple_of(options.frame_every) {
+                write_frame(&mut universe, dir)?;
+            }
+        }
+        if options.stats.is_some() && generation.is_multiple_of(options.stats_every) {
+            rows.push((generation, universe.stats()));
+        }
+    }
+
+    if let Some(path) = &options.stats {
+        write_stats(path, &rows)?;
+    }
+    if let Some(path) = &options.trail {
+        write_png(path, universe.width(), universe.height(), &universe.trail_rgba())?;
+    }
+    if let Some(path) = &options.final_state {
+        fs::write(path, universe.save_state()).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &options.final_png {
+        write_file(path, universe.export_png())?;
+    }
+    if let Some(path) = &options.final_raw {
+        write_file(path, universe.export_png_raw())?;
+    }
+    Ok(())
+}
+
+/// Read `LifeParams` from JSON; missing fields keep their defaults.
+fn load_params(path: &Path) -> Result<LifeParams, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    LifeParams::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Select a palette given as a built-in name or a JSON file path.
+fn load_palette(universe: &mut Universe, name_or_path: &str) -> Result<(), String> {
+    if Palette::named(name_or_path).is_some() {
+        return universe.set_palette(name_or_path);
+    }
+    let json = fs::read_to_string(name_or_path).map_err(|e| format!("{}: {}", name_or_path, e))?;
+    universe.set_palette_json(&json).map_err(|e| format!("{}: {}", name_or_path, e))
+}
+
+/// `MODE` or `MODE:PERSISTENCE`, e.g. `additive:0.9`.
+fn parse_trail_mode(text: &str) -> Result<(TrailMode, f32), String> {
+    let (name, persistence) = text.split_once(':').unwrap_or((text, "1"));
+    let mode = match name.to_ascii_lowercase().as_str() {
+        "additive" => TrailMode::Additive,
+        "max" => TrailMode::Max,
+        "average" => TrailMode::Average,
+        _ => return Err(format!("unknown trail mode '{}'", name)),
+    };
+    let persistence = persistence.parse().map_err(|_| format!("invalid trail persistence '{}'", persistence))?;
+    Ok((mode, persistence))
+}
+
+fn parse_placement(name: &str) -> Result<Placement, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "fit" => Ok(Placement::Fit),
+        "fill" => Ok(Placement::Fill),
+        "stretch" => Ok(Placement::Stretch),
+        "tile" => Ok(Placement::Tile),
+        _ => Err(format!("unknown placement '{}'", name)),
+    }
+}
+
+fn parse_resample(name: &str) -> Result<Resample, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "nearest" => Ok(Resample::Nearest),
+        "bilinear" => Ok(Resample::Bilinear),
+        "area" => Ok(Resample::Area),
+        _ => Err(format!("unknown resampling '{}'", name)),
+    }
+}
+
+fn parse_dither(name: &str) -> Result<Dither, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "none" => Ok(Dither::None),
+        "floyd-steinberg" => Ok(Dither::FloydSteinberg),
+        "ordered" => Ok(Dither::Ordered),
+        _ => Err(format!("unknown dither '{}'", name)),
+    }
+}
+
+fn parse_channel(name: &str) -> Result<LifeChannel, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "hue" => Ok(LifeChannel::Hue),
+        "saturation" => Ok(LifeChannel::Saturation),
+        "luminance" => Ok(LifeChannel::Luminance),
+        "alpha" => Ok(LifeChannel::Alpha),
+        _ => Err(format!("unknown channel '{}'", name)),
+    }
+}
+
+/// Stamp a PNG into the centre of the universe, cropping it to fit.
+fn stamp_image(universe: &mut Universe, path: &Path) -> Result<(), String> {
+    let png = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let image = RgbaImage::decode_png(&png).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (img_w, img_h, rgba) = (image.width, image.height, image.pixels);
+
+    let w = img_w.min(universe.width());
+    let h = img_h.min(universe.height());
+    let (x0, y0) = ((img_w - w) / 2, (img_h - h) / 2);
+    let mut stamp = Vec::with_capacity((w * h * 4) as usize);
+    for row in y0..y0 + h {
+        let start = ((row * img_w + x0) * 4) as usize;
+        stamp.extend_from_slice(&rgba[start..start + (w * 4) as usize]);
+    }
+    universe.draw_stamp_at(universe.width() / 2, universe.height() / 2, w, h, &stamp);
+    Ok(())
+}
+
+/// Write encoded `bytes`, reporting either failure against `path`.
+fn write_file(path: &Path, bytes: Result<Vec<u8>, String>) -> Result<(), String> {
+    let bytes = bytes.map_err(|e| format!("{}: {}", path.display(), e))?;
+    fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_frame(universe: &mut Universe, dir: &Path) -> Result<(), String> {
+    let path = dir.join(format!("frame_{:08}.png", universe.generation()));
+    universe.render();
+    write_png(&path, universe.width(), universe.height(), universe.rgba())
+}
+
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), String> {
+    write_file(path, RgbaImage::new(width, height, rgba.to_vec()).encode_png())
+}
+
+fn write_stats(path: &Path, rows: &[(u64, UniverseStats)]) -> Result<(), String> {
+    let err = |e: std::io::Error| format!("{}: {}", path.display(), e);
+    let mut out = BufWriter::new(File::create(path).map_err(err)?);
+
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        let json: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|(generation, s)| {
+                serde_json::json!({
+                    "generation": generation,
+                    "avg_hue": s.avg_hue(),
+                    "median_hue": s.median_hue(),
+                    "avg_saturation": s.avg_saturation(),
+                    "median_saturation": s.median_saturation(),
+                    "avg_luminance": s.avg_luminance(),
+                    "median_luminance": s.median_luminance(),
+                    "avg_alpha": s.avg_alpha(),
+                    "median_alpha": s.median_alpha(),
+                    "alive_count": s.alive_count(),
+                    "dead_count": s.dead_count(),
+                    "population_ratio": s.population_ratio(),
+                })
+            })
+            .collect();
+        serde_json::to_writer_pretty(&mut out, &json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        writeln!(out).map_err(err)?;
+    } else {
+        writeln!(
+            out,
+            "generation,avg_hue,median_hue,avg_saturation,median_saturation,avg_luminance,\
+             median_luminance,avg_alpha,median_alpha,alive_count,dead_count,population_ratio"
+        )
+        .map_err(err)?;
+        for (generation, s) in rows {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{}",
+                generation,
+                s.avg_hue(), s.median_hue(),
+                s.avg_saturation(), s.median_saturation(),
+                s.avg_luminance(), s.median_luminance(),
+                s.avg_alpha(), s.median_alpha(),
+                s.alive_count(), s.dead_count(),
+                s.population_ratio()
+            )
+            .map_err(err)?;
+        }
+    }
+    out.flush().map_err(err)
+}
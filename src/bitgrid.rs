@@ -0,0 +1,315 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Individual, LifeChannel};
+
+/// A toroidal binary automaton storing one bit per cell in `u64` words.
+///
+/// Only the birth/survival dynamics of `rule` are simulated; colour is
+/// discarded.  Neighbour counts are computed 64 cells at a time with a
+/// bit-sliced ripple adder.
+///
+/// `rule` uses the same 18-bit layout as `LifeParams.rule`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitGrid {
+    width: u32,
+    height: u32,
+    words_per_row: usize,
+    rule: u32,
+    bits: Vec<u64>,
+    next: Vec<u64>,
+    /// Cells that flipped since the last `take_changes`.
+    changed: Vec<u64>,
+    /// Cells the next `step` leaves as they are.
+    held: Vec<u64>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl BitGrid {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(width: u32, height: u32, rule: u32) -> Self {
+        let words_per_row = (width as usize).div_ceil(64);
+        let size = words_per_row * height as usize;
+        Self {
+            width,
+            height,
+            words_per_row,
+            rule,
+            bits: vec![0; size],
+            next: vec![0; size],
+            changed: vec![0; size],
+            held: vec![0; size],
+        }
+    }
+
+    pub fn width(&self) -> u32 { self.width }
+    pub fn height(&self) -> u32 { self.height }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn rule(&self) -> u32 {
+        self.rule
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
+    pub fn set_rule(&mut self, rule: u32) {
+        self.rule = rule;
+    }
+
+    pub fn get(&self, row: u32, col: u32) -> bool {
+        if row >= self.height || col >= self.width {
+            return false;
+        }
+        let (word, bit) = self.locate(row, col);
+        (self.bits[word] >> bit) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: u32, col: u32, alive: bool) {
+        if row >= self.height || col >= self.width {
+            return;
+        }
+        let (word, bit) = self.locate(row, col);
+        if alive {
+            self.bits[word] |= 1 << bit;
+        } else {
+            self.bits[word] &= !(1 << bit);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    /// Number of live cells.
+    pub fn population(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Advance the grid by one generation.
+    pub fn step(&mut self) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        let wpr = self.words_per_row;
+        let height = self.height as usize;
+        let tail_mask = self.tail_mask();
+
+        let mut birth = [false; 9];
+        let mut survive = [false; 9];
+        for n in 0..9 {
+            birth[n] = (self.rule >> n) & 1 == 1;
+            survive[n] = (self.rule >> (n + 9)) & 1 == 1;
+        }
+
+        // West- and east-shifted copies of the previous, current and next rows.
+        let mut north_w = vec![0u64; wpr];
+        let mut north_e = vec![0u64; wpr];
+        let mut mid_w = vec![0u64; wpr];
+        let mut mid_e = vec![0u64; wpr];
+        let mut south_w = vec![0u64; wpr];
+        let mut south_e = vec![0u64; wpr];
+
+        for row in 0..height {
+            let north = (row + height - 1) % height;
+            let south = (row + 1) % height;
+            let north_c = &self.bits[north * wpr..(north + 1) * wpr];
+            let mid_c = &self.bits[row * wpr..(row + 1) * wpr];
+            let south_c = &self.bits[south * wpr..(south + 1) * wpr];
+
+            self.shift_row(north_c, &mut north_w, &mut north_e);
+            self.shift_row(mid_c, &mut mid_w, &mut mid_e);
+            self.shift_row(south_c, &mut south_w, &mut south_e);
+
+            for i in 0..wpr {
+                let inputs = [
+                    north_w[i], north_c[i], north_e[i],
+                    mid_w[i], mid_e[i],
+                    south_w[i], south_c[i], south_e[i],
+                ];
+
+                // Bit-sliced 4-bit counter: sum[k] holds bit k of every lane's count.
+                let mut sum = [0u64; 4];
+                for input in inputs {
+                    let mut carry = input;
+                    for s in sum.iter_mut() {
+                        let next_carry = *s & carry;
+                        *s ^= carry;
+                        carry = next_carry;
+                    }
+                }
+
+                let alive = mid_c[i];
+                let mut born_mask = 0u64;
+                let mut survive_mask = 0u64;
+                for n in 0..9 {
+                    if !birth[n] && !survive[n] {
+                        continue;
+                    }
+                    let eq = count_equals(&sum, n as u32);
+                    if birth[n] { born_mask |= eq; }
+                    if survive[n] { survive_mask |= eq; }
+                }
+
+                let mut word = (alive & survive_mask) | (!alive & born_mask);
+                if i == wpr - 1 {
+                    word &= tail_mask;
+                }
+                let held = self.held[row * wpr + i];
+                word = (word & !held) | (alive & held);
+                self.changed[row * wpr + i] |= word ^ alive;
+                self.next[row * wpr + i] = word;
+            }
+        }
+
+        self.held.fill(0);
+        std::mem::swap(&mut self.bits, &mut self.next);
+    }
+
+    /// Advance the grid by `generations` generations.
+    pub fn step_n(&mut self, generations: u32) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+}
+
+impl BitGrid {
+    /// Pack the life channel of `cells` into a new grid.
+    pub fn from_cells(
+        cells: &[Individual],
+        width: u32,
+        height: u32,
+        rule: u32,
+        channel: LifeChannel,
+    ) -> Self {
+        let mut grid = Self::new(width, height, rule);
+        grid.load_cells(cells, channel);
+        grid
+    }
+
+    /// Replace the grid contents with the life channel of `cells`.
+    pub fn load_cells(&mut self, cells: &[Individual], channel: LifeChannel) {
+        self.bits.fill(0);
+        self.changed.fill(0);
+        self.held.fill(0);
+        let width = self.width as usize;
+        for (row, chunk) in cells.chunks(width.max(1)).take(self.height as usize).enumerate() {
+            let base = row * self.words_per_row;
+            for (col, cell) in chunk.iter().enumerate() {
+                if cell.activity_value(channel) > 0 {
+                    self.bits[base + col / 64] |= 1 << (col % 64);
+                }
+            }
+        }
+    }
+
+    /// Write the grid back into `cells`.
+    ///
+    /// Cells whose liveness did not change keep their colour, cells that
+    /// died are cleared and newly born cells are set to `born`.
+    pub fn store_cells(&self, cells: &mut [Individual], channel: LifeChannel, born: Individual) {
+        let width = self.width as usize;
+        for (row, chunk) in cells.chunks_mut(width.max(1)).take(self.height as usize).enumerate() {
+            let base = row * self.words_per_row;
+            for (col, cell) in chunk.iter_mut().enumerate() {
+                let alive = (self.bits[base + col / 64] >> (col % 64)) & 1 == 1;
+                match (cell.activity_value(channel) > 0, alive) {
+                    (true, false) => *cell = Individual::default(),
+                    (false, true) => *cell = born,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Raw packed words, `words_per_row` per row with bit `col % 64` of word
+    /// `col / 64` holding column `col`.
+    pub fn words(&self) -> &[u64] {
+        &self.bits
+    }
+
+    pub fn words_per_row(&self) -> usize {
+        self.words_per_row
+    }
+
+    /// Keep `(row, col)` as it is for the next generation.
+    pub(crate) fn hold(&mut self, row: u32, col: u32) {
+        if row < self.height && col < self.width {
+            let (word, bit) = self.locate(row, col);
+            self.held[word] |= 1 << bit;
+        }
+    }
+
+    /// Row-major indices of the cells held for the next generation.
+    pub(crate) fn held_cells(&self) -> Vec<usize> {
+        self.indices(&self.held).collect()
+    }
+
+    /// Row-major indices of the cells that flipped since the last call,
+    /// each with whether it is alive now.
+    pub(crate) fn take_changes(&mut self) -> Vec<(usize, bool)> {
+        let changes = self.indices(&self.changed)
+            .map(|idx| {
+                let (row, col) = (idx / self.width as usize, idx % self.width as usize);
+                (idx, self.get(row as u32, col as u32))
+            })
+            .collect();
+        self.changed.fill(0);
+        changes
+    }
+
+    /// Row-major indices of the set bits of `words`, a mask laid out like
+    /// the grid.
+    fn indices<'a>(&self, words: &'a [u64]) -> impl Iterator<Item = usize> + 'a {
+        let (wpr, width) = (self.words_per_row, self.width as usize);
+        words.iter().enumerate().filter(|(_, &w)| w != 0).flat_map(move |(i, &w)| {
+            let base = (i / wpr) * width + (i % wpr) * 64;
+            (0..64).filter(move |bit| (w >> bit) & 1 == 1).map(move |bit| base + bit)
+        })
+    }
+
+    #[inline]
+    fn locate(&self, row: u32, col: u32) -> (usize, u32) {
+        (row as usize * self.words_per_row + col as usize / 64, col % 64)
+    }
+
+    /// Mask of the valid bits in the last word of each row.
+    #[inline]
+    fn tail_mask(&self) -> u64 {
+        match self.width % 64 {
+            0 => u64::MAX,
+            r => (1u64 << r) - 1,
+        }
+    }
+
+    /// Fill `west`/`east` so that bit `x` holds the cell at `x - 1`/`x + 1`,
+    /// wrapping around the row.
+    fn shift_row(&self, row: &[u64], west: &mut [u64], east: &mut [u64]) {
+        let wpr = self.words_per_row;
+        let last_col = self.width as usize - 1;
+        let first = row[0] & 1;
+        let last = (row[last_col / 64] >> (last_col % 64)) & 1;
+
+        for i in 0..wpr {
+            let carry_in = if i == 0 { last } else { row[i - 1] >> 63 };
+            west[i] = (row[i] << 1) | carry_in;
+
+            let carry_out = if i + 1 < wpr { row[i + 1] << 63 } else { 0 };
+            east[i] = (row[i] >> 1) | carry_out;
+        }
+
+        east[wpr - 1] |= first << (last_col % 64);
+        west[wpr - 1] &= self.tail_mask();
+    }
+}
+
+/// Lanes of a bit-sliced 4-bit counter that equal `n`.
+#[inline]
+fn count_equals(sum: &[u64; 4], n: u32) -> u64 {
+    let mut eq = u64::MAX;
+    for (k, s) in sum.iter().enumerate() {
+        eq &= if (n >> k) & 1 == 1 { *s } else { !*s };
+    }
+    eq
+}
@@ -0,0 +1,126 @@
+//! Brush profiles for `Universe::draw_brush_with`.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BrushShape {
+    Circle,
+    Square,
+    Diamond,
+    /// A circle's outer `ring_width` cells.
+    Ring,
+    /// The greyscale mask given to `BrushSettings::set_custom`, stretched
+    /// over the brush's square.
+    Custom,
+}
+
+/// How a brush dab covers the cells around it.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrushSettings {
+    pub shape: BrushShape,
+    pub radius: u32,
+    /// Thickness of a `Ring`, in cells.
+    pub ring_width: u32,
+    /// Fraction of the radius, from the edge inwards, over which the brush
+    /// fades out: 0 for a hard edge, 1 to fade all the way from the centre.
+    /// Faded cells are mixed with their current colour.  A `Custom` mask's
+    /// greys are its own falloff.
+    pub softness: f32,
+    /// Chance of painting each covered cell; below 1 the brush sprays.
+    pub density: f32,
+    /// Each painted cell's hue is offset at random by up to this much
+    /// either way.
+    pub hue_jitter: u8,
+    mask: Vec<u8>,
+    mask_width: u32,
+    mask_height: u32,
+}
+
+impl Default for BrushSettings {
+    fn default() -> Self {
+        Self {
+            shape: BrushShape::Circle,
+            radius: 1,
+            ring_width: 1,
+            softness: 0.0,
+            density: 1.0,
+            hue_jitter: 0,
+            mask: Vec::new(),
+            mask_width: 0,
+            mask_height: 0,
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl BrushSettings {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(shape: BrushShape, radius: u32) -> BrushSettings {
+        BrushSettings { shape, radius, ..BrushSettings::default() }
+    }
+
+    /// Use a `width` x `height` greyscale mask, one byte per pixel with 255
+    /// fully painted, as a `Custom` brush.
+    pub fn set_custom(&mut self, width: u32, height: u32, mask: &[u8]) -> Result<(), String> {
+        let needed = u64::from(width) * u64::from(height);
+        if needed == 0 || mask.len() as u64 != needed {
+            return Err(format!("a {}x{} brush mask needs {} bytes, got {}", width, height, needed, mask.len()));
+        }
+        self.shape = BrushShape::Custom;
+        self.mask = mask.to_vec();
+        (self.mask_width, self.mask_height) = (width, height);
+        Ok(())
+    }
+}
+
+impl BrushSettings {
+    /// How strongly a dab paints the cell `(dx, dy)` from its centre, from
+    /// 0 (untouched) to 1 (the brush colour).
+    pub(crate) fn weight(&self, dx: i32, dy: i32) -> f32 {
+        let r = self.radius as f32;
+        let (x, y) = (dx.abs() as f32, dy.abs() as f32);
+        if x > r || y > r {
+            return 0.0;
+        }
+        // Distance as a fraction of the radius, beyond 1 outside the shape
+        let distance = match self.shape {
+            BrushShape::Circle => (x * x + y * y).sqrt(),
+            BrushShape::Square => x.max(y),
+            BrushShape::Diamond => x + y,
+            BrushShape::Ring => {
+                let d = (x * x + y * y).sqrt();
+                let width = self.ring_width.max(1) as f32;
+                if d > r || d <= r - width {
+                    return 0.0;
+                }
+                // Measured from the middle of the band, scaled so the
+                // softness fades both of its edges
+                return self.falloff(((d - (r - width / 2.0)).abs() / (width / 2.0)).min(1.0));
+            }
+            BrushShape::Custom => {
+                if self.mask.is_empty() {
+                    return 0.0;
+                }
+                let size = 2 * self.radius as i64 + 1;
+                let mx = (dx as i64 + self.radius as i64) * self.mask_width as i64 / size;
+                let my = (dy as i64 + self.radius as i64) * self.mask_height as i64 / size;
+                return self.mask[(my * self.mask_width as i64 + mx) as usize] as f32 / 255.0;
+            }
+        };
+        if distance > r {
+            return 0.0;
+        }
+        self.falloff(if r > 0.0 { distance / r } else { 0.0 })
+    }
+
+    /// Weight at `distance` (0 to 1) from the centre of the brush.
+    fn falloff(&self, distance: f32) -> f32 {
+        if self.softness <= 0.0 {
+            return 1.0;
+        }
+        ((1.0 - distance) / self.softness.min(1.0)).clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,176 @@
+//! Compression for `Universe::save_state_compressed`.
+//!
+//! The container's cells are split into hue, saturation, luminance and
+//! alpha planes and delta coded, so flat areas become runs of zeros.  The
+//! result goes through a greedy LZ77 pass whose matches may overlap their
+//! own output, which makes long runs cost a few bytes each.
+//!
+//! Layout: magic `b"RCAz"`, `u16` version, `u64` length of the original
+//! container, then tokens.  Each token starts with a LEB128 varint `v`:
+//! `v & 1 == 0` is a run of `v >> 1` literal bytes that follow, otherwise a
+//! copy of `v >> 1` bytes from a varint distance back in the output.
+
+use crate::savefile;
+
+pub(crate) const MAGIC: &[u8; 4] = b"RCAz";
+const VERSION: u16 = 1;
+
+const MIN_MATCH: usize = 4;
+const HASH_BITS: u32 = 16;
+
+pub(crate) fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Compress a container written by `savefile::encode`.
+pub(crate) fn compress(container: &[u8]) -> Vec<u8> {
+    let filtered = filter(container, false);
+    let mut out = Vec::with_capacity(64);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(container.len() as u64).to_le_bytes());
+    lz_encode(&filtered, &mut out);
+    out
+}
+
+/// Reverse `compress`.  The container's own checksum is left for
+/// `savefile::decode` to verify.
+pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if !is_compressed(bytes) {
+        return Err("not a compressed universe (bad magic)".to_string());
+    }
+    let header = MAGIC.len() + 2 + 8;
+    if bytes.len() < header {
+        return Err("compressed universe is truncated".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version > VERSION {
+        return Err(format!("compressed universe is format version {}, newer than the supported {}", version, VERSION));
+    }
+    let len = u64::from_le_bytes(bytes[6..14].try_into().unwrap());
+    let len = usize::try_from(len).map_err(|_| "compressed universe is too large".to_string())?;
+    let filtered = lz_decode(&bytes[header..], len)?;
+    Ok(filter(&filtered, true))
+}
+
+/// Planarise and delta code the cells of `container` (or undo it with
+/// `inverse`).  Bytes outside the cell block pass through unchanged.
+fn filter(container: &[u8], inverse: bool) -> Vec<u8> {
+    let mut out = container.to_vec();
+    let Some(cells) = savefile::cells_range(container) else { return out };
+    let count = cells.len() / 4;
+    let (src, dst) = (&container[cells.clone()], &mut out[cells]);
+    for ch in 0..4 {
+        let mut prev = 0u8;
+        for i in 0..count {
+            if inverse {
+                let value = prev.wrapping_add(src[ch * count + i]);
+                dst[i * 4 + ch] = value;
+                prev = value;
+            } else {
+                let value = src[i * 4 + ch];
+                dst[ch * count + i] = value.wrapping_sub(prev);
+                prev = value;
+            }
+        }
+    }
+    out
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn get_varint(bytes: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or("compressed universe is truncated")?;
+        *pos += 1;
+        v |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(v);
+        }
+    }
+    Err("compressed universe has an overlong varint".to_string())
+}
+
+#[inline]
+fn hash(bytes: &[u8]) -> usize {
+    let word = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+    (word.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+fn lz_encode(input: &[u8], out: &mut Vec<u8>) {
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut literals_from = 0;
+    let mut pos = 0;
+
+    let flush = |out: &mut Vec<u8>, from: usize, to: usize| {
+        if to > from {
+            put_varint(out, ((to - from) as u64) << 1);
+            out.extend_from_slice(&input[from..to]);
+        }
+    };
+
+    while pos + MIN_MATCH <= input.len() {
+        let h = hash(&input[pos..]);
+        let candidate = std::mem::replace(&mut table[h], pos);
+        // A run continues its own previous byte, so try distance 1 as well
+        let mut best = (0, 0);
+        for start in [candidate, pos.wrapping_sub(1)] {
+            if start >= pos {
+                continue;
+            }
+            let len = input[pos..].iter().zip(&input[start..]).take_while(|(a, b)| a == b).count();
+            if len > best.0 {
+                best = (len, pos - start);
+            }
+        }
+
+        let (len, distance) = best;
+        if len >= MIN_MATCH {
+            flush(out, literals_from, pos);
+            put_varint(out, ((len as u64) << 1) | 1);
+            put_varint(out, distance as u64);
+            pos += len;
+            literals_from = pos;
+        } else {
+            pos += 1;
+        }
+    }
+    flush(out, literals_from, input.len());
+}
+
+fn lz_decode(bytes: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(len.min(bytes.len().saturating_mul(1024)));
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let token = get_varint(bytes, &mut pos)?;
+        let count = usize::try_from(token >> 1).map_err(|_| "compressed universe is corrupt")?;
+        if count > len - out.len() {
+            return Err("compressed universe expands past its stated size".to_string());
+        }
+        if token & 1 == 0 {
+            let literals = bytes.get(pos..pos + count).ok_or("compressed universe is truncated")?;
+            out.extend_from_slice(literals);
+            pos += count;
+        } else {
+            let distance = get_varint(bytes, &mut pos)? as usize;
+            if distance == 0 || distance > out.len() {
+                return Err("compressed universe refers before its start".to_string());
+            }
+            let start = out.len() - distance;
+            for i in 0..count {
+                out.push(out[start + i]);
+            }
+        }
+    }
+    if out.len() != len {
+        return Err(format!("compressed universe decodes to {} bytes, expected {}", out.len(), len));
+    }
+    Ok(out)
+}
@@ -0,0 +1,205 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{Individual, LifeChannel};
+
+/// Generations of hashes kept by default.
+pub(crate) const DEFAULT_CYCLE_WINDOW: usize = 256;
+/// Generations the population must hold still to count as stagnant.
+pub(crate) const DEFAULT_STAGNATION_WINDOW: u64 = 100;
+
+const MIX: u64 = 0x517C_C1B7_2722_0A95;
+
+/// One cell's share of the state hash.  Fully avalanched, since shares are
+/// summed and any linearity would let different states cancel out.
+#[inline]
+fn cell_hash(idx: usize, value: u64) -> u64 {
+    // Both fit in 32 bits, and the mixing below is a bijection
+    let mut z = ((idx as u64) << 32 | value).wrapping_mul(MIX);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Hash of the life-channel value of each cell in columns `x0..x1` of rows
+/// `y0..y1`, or of all four channels with `full`, plus the number of live
+/// cells.  Each cell's share depends only on its index and value, so the
+/// hashes of disjoint regions sum to the hash of their union.
+pub(crate) fn hash_region(
+    cells: &[Individual],
+    width: u32,
+    (x0, y0, x1, y1): (u32, u32, u32, u32),
+    channel: LifeChannel,
+    full: bool,
+) -> (u64, usize) {
+    let (mut hash, mut alive) = (0u64, 0);
+    for row in y0..y1 {
+        for col in x0..x1 {
+            let idx = (row * width + col) as usize;
+            let c = cells[idx];
+            let value = if full {
+                u32::from_le_bytes([c.hue, c.saturation, c.luminance, c.alpha]) as u64
+            } else {
+                c.activity_value(channel) as u64
+            };
+            hash = hash.wrapping_add(cell_hash(idx, value));
+            alive += (c.activity_value(channel) > 0) as usize;
+        }
+    }
+    (hash, alive)
+}
+
+/// Bounded table of recent state hashes for spotting periodic universes.
+///
+/// A repeated hash means the state recurred; the gap between the two
+/// generations is the period.  Edits void the table since a state reached
+/// by hand says nothing about the dynamics.
+///
+/// The state hash is kept per tile, so after a generation only the tiles it
+/// changed are rehashed; a voided table rehashes every tile.
+pub(crate) struct CycleDetector {
+    pub enabled: bool,
+    pub full: bool,
+    window: usize,
+    stagnation_window: u64,
+    order: VecDeque<(u64, u64)>,
+    seen: HashMap<u64, u64>,
+    stale: bool,
+    period: Option<u64>,
+    start: Option<u64>,
+    alive: Option<usize>,
+    alive_since: u64,
+    last_generation: u64,
+    /// Hash and live count of each tile, summed into `hash` and `population`.
+    tiles: Vec<(u64, usize)>,
+    hash: u64,
+    population: usize,
+}
+
+impl Default for CycleDetector {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            full: false,
+            window: DEFAULT_CYCLE_WINDOW,
+            stagnation_window: DEFAULT_STAGNATION_WINDOW,
+            order: VecDeque::new(),
+            seen: HashMap::new(),
+            stale: false,
+            period: None,
+            start: None,
+            alive: None,
+            alive_since: 0,
+            last_generation: 0,
+            tiles: Vec::new(),
+            hash: 0,
+            population: 0,
+        }
+    }
+}
+
+impl CycleDetector {
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window.max(1);
+        while self.order.len() > self.window {
+            self.evict();
+        }
+    }
+
+    pub fn stagnation_window(&self) -> u64 {
+        self.stagnation_window
+    }
+
+    pub fn set_stagnation_window(&mut self, window: u64) {
+        self.stagnation_window = window.max(1);
+    }
+
+    /// Forget everything seen so far, e.g. after the grid was edited.
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Whether every one of `tiles` tiles must be rehashed before the next
+    /// `observe`, rather than just those that changed.
+    pub fn needs_rehash(&self, tiles: usize) -> bool {
+        self.stale || self.tiles.len() != tiles
+    }
+
+    /// Replace the hash and live count of `tile`.
+    pub fn set_tile(&mut self, tile: usize, hash: u64, alive: usize, tiles: usize) {
+        if self.tiles.len() != tiles {
+            self.tiles = vec![(0, 0); tiles];
+            (self.hash, self.population) = (0, 0);
+        }
+        let (old_hash, old_alive) = std::mem::replace(&mut self.tiles[tile], (hash, alive));
+        self.hash = self.hash.wrapping_sub(old_hash).wrapping_add(hash);
+        self.population = self.population - old_alive + alive;
+    }
+
+    /// Record the state, as summed from the tiles, of `generation`.
+    pub fn observe(&mut self, generation: u64) {
+        let (hash, alive) = (self.hash, self.population);
+        if self.stale {
+            self.stale = false;
+            self.order.clear();
+            self.seen.clear();
+            self.period = None;
+            self.start = None;
+            self.alive = None;
+        }
+
+        match self.seen.get(&hash) {
+            Some(&earlier) => {
+                let period = generation - earlier;
+                if self.period != Some(period) {
+                    self.period = Some(period);
+                    self.start = Some(earlier);
+                }
+            }
+            None => {
+                self.period = None;
+                self.start = None;
+            }
+        }
+
+        if self.alive != Some(alive) {
+            self.alive = Some(alive);
+            self.alive_since = generation;
+        }
+        self.last_generation = generation;
+
+        self.seen.insert(hash, generation);
+        self.order.push_back((hash, generation));
+        while self.order.len() > self.window {
+            self.evict();
+        }
+    }
+
+    pub fn period(&self) -> Option<u64> {
+        self.period
+    }
+
+    pub fn start(&self) -> Option<u64> {
+        self.start
+    }
+
+    pub fn extinct(&self) -> bool {
+        self.alive == Some(0)
+    }
+
+    /// The population has not changed for `stagnation_window` generations.
+    pub fn stagnant(&self) -> bool {
+        self.alive.is_some() && self.last_generation - self.alive_since >= self.stagnation_window
+    }
+
+    fn evict(&mut self) {
+        if let Some((hash, generation)) = self.order.pop_front() {
+            if self.seen.get(&hash) == Some(&generation) {
+                self.seen.remove(&hash);
+            }
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{binary_born_cell, Individual, Universe};
+
+const NONE: u32 = u32::MAX;
+const DEAD: u32 = 0;
+const ALIVE: u32 = 1;
+
+/// Default node count above which `advance` garbage-collects the node store.
+const DEFAULT_MAX_NODES: usize = 1 << 22;
+
+/// Deepest root, keeping the plane's coordinates within `i64`.
+const MAX_LEVEL: u8 = 62;
+
+/// Largest step: a step of `2^j` needs a root of level `j + 3`.
+const MAX_STEP_LOG2: u8 = MAX_LEVEL - 3;
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    level: u8,
+    nw: u32,
+    ne: u32,
+    sw: u32,
+    se: u32,
+    population: u64,
+    /// Memoized RESULT for the current step size, or `NONE`.
+    result: u32,
+}
+
+/// Gosper's HashLife for B0-free birth/survival rules.
+///
+/// The plane is an unbounded quadtree of hash-consed nodes; identical
+/// sub-patterns share a node and each node memoizes its RESULT (the centre
+/// half advanced `2^step_log2` generations).  This lets regular patterns be
+/// advanced thousands or millions of generations in a handful of steps.
+///
+/// Unlike [`Universe`] the plane is not toroidal: patterns imported from a
+/// region evolve freely beyond its edges.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct HashLife {
+    rule: u32,
+    nodes: Vec<Node>,
+    lookup: HashMap<(u32, u32, u32, u32), u32>,
+    empty: Vec<u32>,
+    root: u32,
+    /// Plane coordinates of the root's top-left corner.
+    origin_x: i64,
+    origin_y: i64,
+    generation: u64,
+    step_log2: u8,
+    max_step_log2: u8,
+    max_nodes: usize,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl HashLife {
+    /// Create an empty plane.  Fails for rules with B0, which HashLife
+    /// cannot represent because the empty background would not stay empty.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(rule: u32) -> Result<HashLife, String> {
+        if rule & 1 != 0 {
+            return Err("HashLife does not support B0 rules".to_string());
+        }
+        let leaf = |population| Node {
+            level: 0, nw: NONE, ne: NONE, sw: NONE, se: NONE, population, result: NONE,
+        };
+        let mut life = Self {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            lookup: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            origin_x: 0,
+            origin_y: 0,
+            generation: 0,
+            step_log2: 0,
+            max_step_log2: MAX_STEP_LOG2,
+            max_nodes: DEFAULT_MAX_NODES,
+        };
+        life.root = life.empty_node(3);
+        Ok(life)
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn rule(&self) -> u32 {
+        self.rule
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// Number of nodes currently held in the store.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Node count that triggers garbage collection between steps.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = max_nodes.max(1024);
+    }
+
+    /// Largest power-of-two step `advance` may take in one go, at most
+    /// 59.  Smaller values keep the root small at the cost of more steps.
+    pub fn set_max_step_log2(&mut self, max_step_log2: u8) {
+        self.max_step_log2 = max_step_log2.min(MAX_STEP_LOG2);
+    }
+
+    pub fn get_cell(&self, x: i64, y: i64) -> bool {
+        let level = self.nodes[self.root as usize].level;
+        let size = 1i64 << level;
+        let (Some(mut x), Some(mut y)) = (x.checked_sub(self.origin_x), y.checked_sub(self.origin_y)) else {
+            return false;
+        };
+        if x < 0 || y < 0 || x >= size || y >= size {
+            return false;
+        }
+        let mut node = self.root;
+        let mut half = size >> 1;
+        while half > 0 {
+            let n = self.nodes[node as usize];
+            node = match (x >= half, y >= half) {
+                (false, false) => n.nw,
+                (true, false) => n.ne,
+                (false, true) => n.sw,
+                (true, true) => n.se,
+            };
+            if x >= half { x -= half; }
+            if y >= half { y -= half; }
+            half >>= 1;
+        }
+        node == ALIVE
+    }
+
+    /// Set a cell; cells beyond the plane's `2^62` span are ignored.
+    pub fn set_cell(&mut self, x: i64, y: i64, alive: bool) {
+        loop {
+            let size = 1i64 << self.nodes[self.root as usize].level;
+            let (lx, ly) = (x as i128 - self.origin_x as i128, y as i128 - self.origin_y as i128);
+            if lx >= 0 && ly >= 0 && lx < size as i128 && ly < size as i128 {
+                self.root = self.set_in(self.root, lx as i64, ly as i64, alive);
+                return;
+            }
+            if !self.expand() {
+                return;
+            }
+        }
+    }
+
+    /// Clear the plane and reset the generation counter.
+    pub fn clear(&mut self) {
+        self.root = self.empty_node(3);
+        self.origin_x = 0;
+        self.origin_y = 0;
+        self.generation = 0;
+    }
+
+    /// Advance the pattern by `generations`, decomposed into power-of-two
+    /// steps no larger than `2^max_step_log2`.  Stops early, short of
+    /// `generations`, if the pattern would outgrow the plane's `2^62` span.
+    pub fn advance(&mut self, generations: u64) {
+        let mut remaining = generations;
+        let mut bit = 0u8;
+        while remaining > 0 {
+            if bit >= self.max_step_log2 {
+                let step = 1u64 << self.max_step_log2;
+                while remaining >= step {
+                    if !self.step(self.max_step_log2) {
+                        return;
+                    }
+                    remaining -= step;
+                }
+                bit = 0;
+                continue;
+            }
+            if remaining & (1 << bit) != 0 {
+                if !self.step(bit) {
+                    return;
+                }
+                remaining &= !(1 << bit);
+            }
+            bit += 1;
+        }
+    }
+
+    /// Replace the plane with the live cells of a `Universe` region; cell
+    /// `(x + dx, y + dy)` of the universe becomes plane cell `(dx, dy)`.
+    pub fn import_region(&mut self, universe: &Universe, x: u32, y: u32, w: u32, h: u32) {
+        let channel = universe.params.life_channel;
+        let x_end = x.saturating_add(w).min(universe.width);
+        let y_end = y.saturating_add(h).min(universe.height);
+        let w = x_end.saturating_sub(x) as i64;
+        let h = y_end.saturating_sub(y) as i64;
+
+        let mut level = 3u8;
+        while (1i64 << level) < w.max(h) {
+            level += 1;
+        }
+        let alive = |px: i64, py: i64| {
+            if px >= w || py >= h {
+                return false;
+            }
+            let idx = universe.index(y + py as u32, x + px as u32);
+            universe.cells[idx].activity_value(channel) > 0
+        };
+
+        self.root = self.build(level, 0, 0, &alive);
+        self.origin_x = 0;
+        self.origin_y = 0;
+        self.generation = 0;
+    }
+
+    /// Write plane cells `(0, 0)..(w, h)` into a `Universe` region at
+    /// `(x, y)`.  Cells staying alive keep their colour, dead cells are
+    /// cleared and newly alive cells are drawn at full strength.  Like a
+    /// load, this drops the universe's history and undo journal.
+    pub fn export_region(&self, universe: &mut Universe, x: u32, y: u32, w: u32, h: u32) {
+        let channel = universe.params.life_channel;
+        let born = binary_born_cell(channel);
+        let x_end = x.saturating_add(w);
+        let y_end = y.saturating_add(h);
+
+        universe.replace_region((x, y, x_end, y_end), |row, col, cell| {
+            let alive = self.get_cell((col - x) as i64, (row - y) as i64);
+            match (cell.activity_value(channel) > 0, alive) {
+                (true, false) => Individual::default(),
+                (false, true) => born,
+                _ => cell,
+            }
+        });
+    }
+}
+
+impl HashLife {
+    /// Live cells as plane coordinates.
+    pub fn live_cells(&self) -> Vec<(i64, i64)> {
+        let mut out = Vec::with_capacity(self.population() as usize);
+        let mut stack = vec![(self.root, self.origin_x, self.origin_y)];
+        while let Some((node, x, y)) = stack.pop() {
+            let n = self.nodes[node as usize];
+            if n.population == 0 {
+                continue;
+            }
+            if n.level == 0 {
+                out.push((x, y));
+                continue;
+            }
+            let half = 1i64 << (n.level - 1);
+            stack.push((n.nw, x, y));
+            stack.push((n.ne, x + half, y));
+            stack.push((n.sw, x, y + half));
+            stack.push((n.se, x + half, y + half));
+        }
+        out
+    }
+
+    fn join(&mut self, nw: u32, ne: u32, sw: u32, se: u32) -> u32 {
+        if let Some(&idx) = self.lookup.get(&(nw, ne, sw, se)) {
+            return idx;
+        }
+        let population = [nw, ne, sw, se]
+            .iter()
+            .map(|&c| self.nodes[c as usize].population)
+            .fold(0u64, u64::saturating_add);
+        let idx = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            level: self.nodes[nw as usize].level + 1,
+            nw, ne, sw, se,
+            population,
+            result: NONE,
+        });
+        self.lookup.insert((nw, ne, sw, se), idx);
+        idx
+    }
+
+    fn empty_node(&mut self, level: u8) -> u32 {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let next = self.join(e, e, e, e);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+
+    fn build(&mut self, level: u8, x: i64, y: i64, alive: &dyn Fn(i64, i64) -> bool) -> u32 {
+        if level == 0 {
+            return if alive(x, y) { ALIVE } else { DEAD };
+        }
+        let half = 1i64 << (level - 1);
+        let nw = self.build(level - 1, x, y, alive);
+        let ne = self.build(level - 1, x + half, y, alive);
+        let sw = self.build(level - 1, x, y + half, alive);
+        let se = self.build(level - 1, x + half, y + half, alive);
+        self.join(nw, ne, sw, se)
+    }
+
+    fn set_in(&mut self, node: u32, x: i64, y: i64, alive: bool) -> u32 {
+        let n = self.nodes[node as usize];
+        if n.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1i64 << (n.level - 1);
+        let (mut nw, mut ne, mut sw, mut se) = (n.nw, n.ne, n.sw, n.se);
+        match (x >= half, y >= half) {
+            (false, false) => nw = self.set_in(nw, x, y, alive),
+            (true, false) => ne = self.set_in(ne, x - half, y, alive),
+            (false, true) => sw = self.set_in(sw, x, y - half, alive),
+            (true, true) => se = self.set_in(se, x - half, y - half, alive),
+        }
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Grow the root by one level, keeping the pattern centred.  Returns
+    /// false, leaving it alone, once it is `MAX_LEVEL` deep.
+    fn expand(&mut self) -> bool {
+        let root = self.nodes[self.root as usize];
+        if root.level >= MAX_LEVEL {
+            return false;
+        }
+        let e = self.empty_node(root.level - 1);
+        let nw = self.join(e, e, e, root.nw);
+        let ne = self.join(e, e, root.ne, e);
+        let sw = self.join(e, root.sw, e, e);
+        let se = self.join(root.se, e, e, e);
+        self.root = self.join(nw, ne, sw, se);
+        let shift = 1i64 << (root.level - 1);
+        self.origin_x -= shift;
+        self.origin_y -= shift;
+        true
+    }
+
+    fn centre(&mut self, node: u32) -> u32 {
+        let n = self.nodes[node as usize];
+        let (nw, ne, sw, se) = (
+            self.nodes[n.nw as usize],
+            self.nodes[n.ne as usize],
+            self.nodes[n.sw as usize],
+            self.nodes[n.se as usize],
+        );
+        self.join(nw.se, ne.sw, sw.ne, se.nw)
+    }
+
+    /// Advance the whole pattern by `2^j` generations.  Returns false,
+    /// without advancing, if the root would have to grow past `MAX_LEVEL`.
+    fn step(&mut self, j: u8) -> bool {
+        if self.nodes.len() > self.max_nodes {
+            self.collect_garbage();
+        }
+        if j != self.step_log2 {
+            self.step_log2 = j;
+            for node in self.nodes.iter_mut() {
+                node.result = NONE;
+            }
+        }
+
+        // The pattern must sit in the centre quarter of a root at least
+        // `j + 3` levels deep so it cannot outrun the RESULT square.
+        loop {
+            let level = self.nodes[self.root as usize].level;
+            if level >= j + 3 {
+                let inner = self.centre(self.root);
+                let inner = self.centre(inner);
+                if self.nodes[inner as usize].population == self.population() {
+                    break;
+                }
+            }
+            if !self.expand() {
+                return false;
+            }
+        }
+
+        let level = self.nodes[self.root as usize].level;
+        self.root = self.result(self.root);
+        let shift = 1i64 << (level - 2);
+        self.origin_x += shift;
+        self.origin_y += shift;
+        self.generation += 1 << j;
+        true
+    }
+
+    /// The centre half of `node` advanced `2^min(step_log2, level - 2)` generations.
+    fn result(&mut self, node: u32) -> u32 {
+        let n = self.nodes[node as usize];
+        if n.result != NONE {
+            return n.result;
+        }
+        let result = if n.population == 0 {
+            self.empty_node(n.level - 1)
+        } else if n.level == 2 {
+            self.base_case(node)
+        } else {
+            let full_speed = self.step_log2 as u32 + 2 >= n.level as u32;
+            let (nw, ne, sw, se) = (
+                self.nodes[n.nw as usize],
+                self.nodes[n.ne as usize],
+                self.nodes[n.sw as usize],
+                self.nodes[n.se as usize],
+            );
+
+            let subs = [
+                n.nw,
+                self.join(nw.ne, ne.nw, nw.se, ne.sw),
+                n.ne,
+                self.join(nw.sw, nw.se, sw.nw, sw.ne),
+                self.join(nw.se, ne.sw, sw.ne, se.nw),
+                self.join(ne.sw, ne.se, se.nw, se.ne),
+                n.sw,
+                self.join(sw.ne, se.nw, sw.se, se.sw),
+                n.se,
+            ];
+            let mut r = [0u32; 9];
+            for (dst, &sub) in r.iter_mut().zip(subs.iter()) {
+                *dst = if full_speed { self.result(sub) } else { self.centre(sub) };
+            }
+
+            let a = self.join(r[0], r[1], r[3], r[4]);
+            let b = self.join(r[1], r[2], r[4], r[5]);
+            let c = self.join(r[3], r[4], r[6], r[7]);
+            let d = self.join(r[4], r[5], r[7], r[8]);
+            let (a, b, c, d) = (self.result(a), self.result(b), self.result(c), self.result(d));
+            self.join(a, b, c, d)
+        };
+        self.nodes[node as usize].result = result;
+        result
+    }
+
+    /// One generation of a 4x4 node, yielding its centre 2x2.
+    fn base_case(&mut self, node: u32) -> u32 {
+        let n = self.nodes[node as usize];
+        let mut grid = [[false; 4]; 4];
+        for (quadrant, (ox, oy)) in [(n.nw, (0, 0)), (n.ne, (2, 0)), (n.sw, (0, 2)), (n.se, (2, 2))] {
+            let q = self.nodes[quadrant as usize];
+            grid[oy][ox] = q.nw == ALIVE;
+            grid[oy][ox + 1] = q.ne == ALIVE;
+            grid[oy + 1][ox] = q.sw == ALIVE;
+            grid[oy + 1][ox + 1] = q.se == ALIVE;
+        }
+
+        let mut next = [DEAD; 4];
+        for (i, (x, y)) in [(1, 1), (2, 1), (1, 2), (2, 2)].into_iter().enumerate() {
+            let count = (0..9)
+                .filter(|&k| k != 4 && grid[y - 1 + k / 3][x - 1 + k % 3])
+                .count();
+            let bit = count + if grid[y][x] { 9 } else { 0 };
+            if (self.rule >> bit) & 1 == 1 {
+                next[i] = ALIVE;
+            }
+        }
+        self.join(next[0], next[1], next[2], next[3])
+    }
+
+    /// Drop every node not reachable from the root or its memoized results.
+    fn collect_garbage(&mut self) {
+        let mut marked = vec![false; self.nodes.len()];
+        marked[DEAD as usize] = true;
+        marked[ALIVE as usize] = true;
+        let mut stack = vec![self.root];
+        while let Some(idx) = stack.pop() {
+            if idx == NONE || marked[idx as usize] {
+                continue;
+            }
+            marked[idx as usize] = true;
+            let n = self.nodes[idx as usize];
+            stack.extend([n.nw, n.ne, n.sw, n.se, n.result]);
+        }
+
+        let mut remap = vec![NONE; self.nodes.len()];
+        let mut next_idx = 0u32;
+        for (idx, &keep) in marked.iter().enumerate() {
+            if keep {
+                remap[idx] = next_idx;
+                next_idx += 1;
+            }
+        }
+
+        let map = |idx: u32| if idx == NONE { NONE } else { remap[idx as usize] };
+        let mut nodes = Vec::with_capacity(next_idx as usize);
+        self.lookup.clear();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if !marked[idx] {
+                continue;
+            }
+            let mut node = *node;
+            if node.level > 0 {
+                node.nw = map(node.nw);
+                node.ne = map(node.ne);
+                node.sw = map(node.sw);
+                node.se = map(node.se);
+                self.lookup.insert((node.nw, node.ne, node.sw, node.se), nodes.len() as u32);
+            }
+            node.result = map(node.result);
+            nodes.push(node);
+        }
+
+        self.root = map(self.root);
+        self.nodes = nodes;
+        self.empty.truncate(1);
+    }
+}
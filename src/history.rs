@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+
+use crate::Individual;
+
+/// One cell overwritten by a generation or an edit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct CellChange {
+    pub idx: u32,
+    pub old: Individual,
+    pub new: Individual,
+}
+
+/// A cell edited between generations.  Drawn edits are `held`: the cell
+/// skips its next generation; toggles are not.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Edit {
+    pub change: CellChange,
+    pub held: bool,
+}
+
+/// Everything needed to move between two consecutive generations.
+#[derive(Clone, Debug)]
+pub(crate) struct Frame {
+    /// Edits made at the earlier generation, before it was ticked.
+    pub edits: Vec<Edit>,
+    /// Cells the tick itself changed.
+    pub tick: Vec<CellChange>,
+    pub rng_before: u64,
+    pub rng_after: u64,
+}
+
+/// Ring buffer of the last `capacity` generations, each stored as the
+/// cells it changed relative to the one before.
+///
+/// `cursor` frames are applied; frames past it can be replayed with
+/// `Universe::step_forward` until the timeline is edited or ticked again.
+#[derive(Default)]
+pub(crate) struct History {
+    capacity: usize,
+    frames: VecDeque<Frame>,
+    cursor: usize,
+    pending: Vec<Edit>,
+}
+
+impl History {
+    pub fn enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        if capacity == 0 {
+            self.clear();
+            return;
+        }
+        while self.frames.len() > capacity {
+            self.drop_oldest();
+        }
+    }
+
+    /// Forget every recorded generation, e.g. after the grid was replaced.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.cursor = 0;
+        self.pending.clear();
+    }
+
+    /// Generations that can be stepped back.
+    pub fn back_len(&self) -> usize {
+        self.cursor
+    }
+
+    /// Generations that can be replayed forward.
+    pub fn forward_len(&self) -> usize {
+        self.frames.len() - self.cursor
+    }
+
+    /// Record an edit made at the current generation.
+    pub fn record_edit(&mut self, change: CellChange, held: bool) {
+        if !self.enabled() {
+            return;
+        }
+        self.frames.truncate(self.cursor);
+        self.pending.push(Edit { change, held });
+    }
+
+    /// Record a generation and the edits that preceded it.
+    pub fn commit(&mut self, tick: Vec<CellChange>, rng_before: u64, rng_after: u64) {
+        self.frames.truncate(self.cursor);
+        self.frames.push_back(Frame {
+            edits: std::mem::take(&mut self.pending),
+            tick,
+            rng_before,
+            rng_after,
+        });
+        self.cursor = self.frames.len();
+        while self.frames.len() > self.capacity {
+            self.drop_oldest();
+        }
+    }
+
+    /// Edits made since the current generation was reached.
+    pub fn take_pending(&mut self) -> Vec<Edit> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Move the cursor back, returning the frame that produced the current
+    /// generation.  Its edits become pending again.
+    pub fn back(&mut self) -> Option<&Frame> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        let frame = &self.frames[self.cursor];
+        self.pending = frame.edits.clone();
+        Some(frame)
+    }
+
+    /// Move the cursor forward, returning the frame to replay.
+    pub fn forward(&mut self) -> Option<&Frame> {
+        let frame = self.frames.get(self.cursor)?;
+        self.cursor += 1;
+        self.pending.clear();
+        Some(frame)
+    }
+
+    fn drop_oldest(&mut self) {
+        if self.frames.pop_front().is_some() {
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! PNG encoding and decoding in pure Rust, for the CLI and wasm alike, and
+//! resampling.
+
+use std::ops::Range;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// How `RgbaImage::resize` computes each output pixel.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Resample {
+    /// The source pixel under the output pixel's centre; keeps values exact.
+    Nearest,
+    /// Interpolates the four nearest source pixels; smooth when enlarging.
+    Bilinear,
+    /// Averages every source pixel the output pixel covers; best for
+    /// shrinking photos.
+    Area,
+}
+
+/// `tEXt` keyword marking a PNG whose channels hold raw cell HSLA.
+const RAW_KEYWORD: &str = "ca:cells";
+const RAW_TEXT: &str = "hsla";
+
+/// An 8-bit straight-alpha RGBA image.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    /// The channels are raw cell HSLA written by `Universe::export_png_raw`
+    /// rather than colours.
+    pub raw_hsla: bool,
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        Self { width, height, pixels, raw_hsla: false }
+    }
+
+    /// Decode a PNG of any colour type and bit depth to 8-bit RGBA.
+    pub fn decode_png(bytes: &[u8]) -> Result<RgbaImage, String> {
+        let err = |e: png::DecodingError| format!("invalid PNG: {}", e);
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(err)?;
+        let raw_hsla = reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .any(|chunk| chunk.keyword == RAW_KEYWORD && chunk.text == RAW_TEXT);
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(err)?;
+
+        let count = info.width as usize * info.height as usize;
+        let pixels: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => buf[..count * 4].to_vec(),
+            png::ColorType::Rgb => buf.chunks_exact(3).take(count).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => {
+                buf.chunks_exact(2).take(count).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect()
+            }
+            png::ColorType::Grayscale => buf.iter().take(count).flat_map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => return Err("unexpanded palette PNG".to_string()),
+        };
+        Ok(RgbaImage { width: info.width, height: info.height, pixels, raw_hsla })
+    }
+
+    /// Encode as an RGBA PNG, tagged if it holds raw HSLA.
+    pub fn encode_png(&self) -> Result<Vec<u8>, String> {
+        let err = |e: png::EncodingError| format!("cannot encode PNG: {}", e);
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        if self.raw_hsla {
+            encoder.add_text_chunk(RAW_KEYWORD.to_string(), RAW_TEXT.to_string()).map_err(err)?;
+        }
+        let mut writer = encoder.write_header().map_err(err)?;
+        writer.write_image_data(&self.pixels).map_err(err)?;
+        writer.finish().map_err(err)?;
+        Ok(out)
+    }
+
+    /// Scale to `width` x `height`.  Filtering works on premultiplied
+    /// alpha so transparent pixels don't darken their neighbours.
+    pub fn resize(&self, width: u32, height: u32, resample: Resample) -> RgbaImage {
+        self.resize_window(width, height, resample, (0, 0, width, height))
+    }
+
+    /// The `(x, y, w, h)` window of this image scaled to `width` x
+    /// `height`, computing only the pixels inside it.  The window must lie
+    /// within the scaled size.
+    pub(crate) fn resize_window(
+        &self,
+        width: u32,
+        height: u32,
+        resample: Resample,
+        (x, y, w, h): (u32, u32, u32, u32),
+    ) -> RgbaImage {
+        if self.pixels.is_empty() || w == 0 || h == 0 {
+            let pixels = vec![0; w as usize * h as usize * 4];
+            return RgbaImage { width: w, height: h, pixels, raw_hsla: self.raw_hsla };
+        }
+        if resample == Resample::Nearest || (width, height) == (self.width, self.height) {
+            let mut pixels = Vec::with_capacity(w as usize * h as usize * 4);
+            for row in y..y + h {
+                let sy = (row as u64 * self.height as u64 / height as u64) as usize;
+                for col in x..x + w {
+                    let sx = (col as u64 * self.width as u64 / width as u64) as usize;
+                    let i = (sy * self.width as usize + sx) * 4;
+                    pixels.extend_from_slice(&self.pixels[i..i + 4]);
+                }
+            }
+            return RgbaImage { width: w, height: h, pixels, raw_hsla: self.raw_hsla };
+        }
+        let premultiplied: Vec<f32> = self
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let a = p[3] as f32 / 255.0;
+                [p[0] as f32 * a, p[1] as f32 * a, p[2] as f32 * a, p[3] as f32]
+            })
+            .collect();
+
+        // Separable: rows first, then columns
+        let (sw, sh) = (self.width as usize, self.height as usize);
+        let (dw, dh) = (w as usize, h as usize);
+        let mut rows = vec![0f32; dw * sh * 4];
+        for (x, taps) in weights(self.width, width, resample, x..x + w).iter().enumerate() {
+            for y in 0..sh {
+                for &(sx, w) in taps {
+                    for ch in 0..4 {
+                        rows[(y * dw + x) * 4 + ch] += premultiplied[(y * sw + sx) * 4 + ch] * w;
+                    }
+                }
+            }
+        }
+        let mut out = vec![0f32; dw * dh * 4];
+        for (y, taps) in weights(self.height, height, resample, y..y + h).iter().enumerate() {
+            for &(sy, w) in taps {
+                for (dst, src) in out[y * dw * 4..(y + 1) * dw * 4].iter_mut().zip(&rows[sy * dw * 4..]) {
+                    *dst += src * w;
+                }
+            }
+        }
+
+        let byte = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+        let pixels = out
+            .chunks_exact(4)
+            .flat_map(|p| {
+                if p[3] <= 0.0 {
+                    return [0; 4];
+                }
+                let a = p[3] / 255.0;
+                [byte(p[0] / a), byte(p[1] / a), byte(p[2] / a), byte(p[3])]
+            })
+            .collect();
+        RgbaImage { width: w, height: h, pixels, raw_hsla: self.raw_hsla }
+    }
+
+    /// Scale to `width` x `height` by picking the nearest pixel, which keeps
+    /// raw HSLA values intact.
+    pub fn resize_nearest(&self, width: u32, height: u32) -> RgbaImage {
+        if (width, height) == (self.width, self.height) {
+            return self.clone();
+        }
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let sy = (y as u64 * self.height as u64 / height as u64) as usize;
+            for x in 0..width {
+                let sx = (x as u64 * self.width as u64 / width as u64) as usize;
+                let i = (sy * self.width as usize + sx) * 4;
+                pixels.extend_from_slice(&self.pixels[i..i + 4]);
+            }
+        }
+        RgbaImage { width, height, pixels, raw_hsla: self.raw_hsla }
+    }
+}
+
+/// For each output position in `range` of `dst` along an axis, the source
+/// positions it reads and their weights, summing to 1.
+fn weights(src: u32, dst: u32, resample: Resample, range: Range<u32>) -> Vec<Vec<(usize, f32)>> {
+    let ratio = src as f64 / dst as f64;
+    let last = src as usize - 1;
+    range
+        .map(|i| match resample {
+            Resample::Nearest => vec![((((i as f64 + 0.5) * ratio) as usize).min(last), 1.0)],
+            Resample::Bilinear => {
+                let centre = ((i as f64 + 0.5) * ratio - 0.5).max(0.0);
+                let (lo, f) = (centre.floor() as usize, centre.fract() as f32);
+                if lo >= last {
+                    vec![(last, 1.0)]
+                } else {
+                    vec![(lo, 1.0 - f), (lo + 1, f)]
+                }
+            }
+            Resample::Area => {
+                let (start, end) = (i as f64 * ratio, (i as f64 + 1.0) * ratio);
+                let mut taps = Vec::new();
+                let mut j = start.floor() as usize;
+                while (j as f64) < end && j <= last {
+                    let overlap = end.min(j as f64 + 1.0) - start.max(j as f64);
+                    if overlap > 0.0 {
+                        taps.push((j, (overlap / (end - start)) as f32));
+                    }
+                    j += 1;
+                }
+                taps
+            }
+        })
+        .collect()
+}
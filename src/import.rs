@@ -0,0 +1,198 @@
+//! Placing images onto the grid for `Universe::import_image`.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Resample, RgbaImage};
+
+/// How an image is fitted into the target region.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Placement {
+    /// Scale to fit inside, keeping the aspect ratio, and centre.
+    Fit,
+    /// Scale to cover it, keeping the aspect ratio, and crop the overflow.
+    Fill,
+    /// Scale to exactly its size.
+    Stretch,
+    /// Repeat at the image's own size.
+    Tile,
+}
+
+/// Dithering of the life channel, turning shades into scattered live cells.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Dither {
+    /// Keep the converted colours as they are.
+    None,
+    /// Error diffusion; organic, evenly spread seeds.
+    FloydSteinberg,
+    /// An 8x8 Bayer matrix; regular patterns, stable under small changes.
+    Ordered,
+}
+
+/// Options for `Universe::import_image`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ImportOptions {
+    pub resample: Resample,
+    pub placement: Placement,
+    pub dither: Dither,
+    /// Target region; a zero width or height means the whole grid.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            resample: Resample::Area,
+            placement: Placement::Fit,
+            dither: Dither::None,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ImportOptions {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(resample: Resample, placement: Placement, dither: Dither) -> ImportOptions {
+        ImportOptions { resample, placement, dither, ..ImportOptions::default() }
+    }
+
+    /// Import into the `width` x `height` region at `(x, y)` only.
+    pub fn set_region(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        (self.x, self.y, self.width, self.height) = (x, y, width, height);
+    }
+}
+
+impl ImportOptions {
+    /// The target region `(x, y, width, height)` clipped to a grid, or
+    /// `None` if nothing of it is on the grid.
+    pub(crate) fn region(&self, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        if self.width == 0 || self.height == 0 {
+            return (width > 0 && height > 0).then_some((0, 0, width, height));
+        }
+        if self.x >= width || self.y >= height {
+            return None;
+        }
+        Some((self.x, self.y, self.width.min(width - self.x), self.height.min(height - self.y)))
+    }
+}
+
+/// `image` placed into a `width` x `height` region; pixels it doesn't
+/// cover are transparent.
+pub(crate) fn place(image: &RgbaImage, width: u32, height: u32, options: &ImportOptions) -> RgbaImage {
+    let mut out = RgbaImage::new(width, height, vec![0; width as usize * height as usize * 4]);
+    if image.width == 0 || image.height == 0 {
+        return out;
+    }
+    let (iw, ih) = (image.width as f64, image.height as f64);
+    let scaled_size = |scale: f64| (((iw * scale).round() as u32).max(1), ((ih * scale).round() as u32).max(1));
+    // The placed image and where its top-left lands, possibly off-region
+    let (placed, dx, dy) = match options.placement {
+        Placement::Stretch => (image.resize(width, height, options.resample), 0i64, 0i64),
+        Placement::Tile => {
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let src = ((y % image.height as usize) * image.width as usize + x % image.width as usize) * 4;
+                    let dst = (y * width as usize + x) * 4;
+                    out.pixels[dst..dst + 4].copy_from_slice(&image.pixels[src..src + 4]);
+                }
+            }
+            return out;
+        }
+        Placement::Fit => {
+            let (w, h) = scaled_size((width as f64 / iw).min(height as f64 / ih));
+            let dx = (width as i64 - w as i64) / 2;
+            let dy = (height as i64 - h as i64) / 2;
+            (image.resize(w, h, options.resample), dx, dy)
+        }
+        Placement::Fill => {
+            // Only the centred window that shows is scaled, however far the
+            // image overflows the region
+            let (w, h) = scaled_size((width as f64 / iw).max(height as f64 / ih));
+            let (x0, y0) = (w.saturating_sub(width) / 2, h.saturating_sub(height) / 2);
+            let window = (x0, y0, width.min(w), height.min(h));
+            (image.resize_window(w, h, options.resample, window), 0, 0)
+        }
+    };
+    for y in 0..height as i64 {
+        let py = y - dy;
+        if py < 0 || py >= placed.height as i64 {
+            continue;
+        }
+        for x in 0..width as i64 {
+            let px = x - dx;
+            if px < 0 || px >= placed.width as i64 {
+                continue;
+            }
+            let src = (py as usize * placed.width as usize + px as usize) * 4;
+            let dst = (y as usize * width as usize + x as usize) * 4;
+            out.pixels[dst..dst + 4].copy_from_slice(&placed.pixels[src..src + 4]);
+        }
+    }
+    out
+}
+
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Which pixels of `image` become live cells: its brightness (luma times
+/// alpha) dithered to on or off, or `None` without dithering.  `(x0, y0)`
+/// anchors the ordered pattern to grid coordinates.
+pub(crate) fn dither(image: &RgbaImage, method: Dither, x0: u32, y0: u32) -> Option<Vec<bool>> {
+    let intensity: Vec<f32> = image
+        .pixels
+        .chunks_exact(4)
+        .map(|p| (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) / 255.0 * p[3] as f32 / 255.0)
+        .collect();
+    let (w, h) = (image.width as usize, image.height as usize);
+    let on = match method {
+        Dither::None => return None,
+        Dither::Ordered => (0..w * h)
+            .map(|i| {
+                let (x, y) = ((i % w) as u32 + x0, (i / w) as u32 + y0);
+                let threshold = (BAYER_8X8[y as usize % 8][x as usize % 8] as f32 + 0.5) / 64.0;
+                intensity[i] > threshold
+            })
+            .collect(),
+        Dither::FloydSteinberg => {
+            let mut values = intensity;
+            let mut on = vec![false; w * h];
+            for y in 0..h {
+                for x in 0..w {
+                    let i = y * w + x;
+                    on[i] = values[i] >= 0.5;
+                    let error = values[i] - if on[i] { 1.0 } else { 0.0 };
+                    let mut spread = |dx: isize, dy: usize, weight: f32| {
+                        let nx = x as isize + dx;
+                        if nx >= 0 && (nx as usize) < w && y + dy < h {
+                            values[(y + dy) * w + nx as usize] += error * weight;
+                        }
+                    };
+                    spread(1, 0, 7.0 / 16.0);
+                    spread(-1, 1, 3.0 / 16.0);
+                    spread(0, 1, 5.0 / 16.0);
+                    spread(1, 1, 1.0 / 16.0);
+                }
+            }
+            on
+        }
+    };
+    Some(on)
+}
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::history::CellChange;
+
+/// Default memory cap of the edit journal, in bytes.
+pub(crate) const DEFAULT_JOURNAL_LIMIT: usize = 16 << 20;
+
+/// What produced a journal entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum EditKind {
+    /// Every dab of a `draw_brush` stroke sharing one `brush_id`.
+    Stroke(u64),
+    /// One `draw_stamp` call, image import or pattern placement.
+    Stamp,
+}
+
+struct Entry {
+    kind: EditKind,
+    /// One change per cell: its value before the edit and the latest one.
+    changes: Vec<CellChange>,
+}
+
+impl Entry {
+    fn bytes(&self) -> usize {
+        self.changes.len() * std::mem::size_of::<CellChange>()
+    }
+}
+
+/// Undo/redo stacks of user edits, independent of the generation history.
+///
+/// Entries are dropped oldest first once their combined size exceeds
+/// `limit`; the entry being recorded is always kept.
+pub(crate) struct EditJournal {
+    limit: usize,
+    bytes: usize,
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    recording: bool,
+    /// Position of each cell in the newest undo entry, if built.
+    index: Option<HashMap<u32, usize>>,
+}
+
+impl Default for EditJournal {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_JOURNAL_LIMIT,
+            bytes: 0,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            recording: false,
+            index: None,
+        }
+    }
+}
+
+impl EditJournal {
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Cap the journal at `limit` bytes; `0` disables and empties it.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        if limit == 0 {
+            self.clear();
+        } else {
+            self.enforce_limit();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.bytes = 0;
+        self.recording = false;
+        self.index = None;
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Start recording `kind`.  Further dabs of the newest stroke extend it
+    /// rather than opening a new entry.
+    pub fn begin(&mut self, kind: EditKind) {
+        if self.limit == 0 {
+            return;
+        }
+        self.recording = true;
+        let extends = matches!(kind, EditKind::Stroke(_))
+            && self.undo.back().is_some_and(|entry| entry.kind == kind);
+        if !extends {
+            self.undo.push_back(Entry { kind, changes: Vec::new() });
+            self.index = Some(HashMap::new());
+        }
+    }
+
+    /// Stop recording until the next `begin`, dropping the entry if it
+    /// ended up changing nothing.
+    pub fn end(&mut self) {
+        if !self.recording {
+            return;
+        }
+        self.recording = false;
+        if self.undo.back().is_some_and(|entry| entry.changes.is_empty()) {
+            self.undo.pop_back();
+            self.index = None;
+        }
+    }
+
+    /// Record a cell change if an edit is open.
+    pub fn record(&mut self, change: CellChange) {
+        if !self.recording || change.old == change.new {
+            return;
+        }
+        let Some(entry) = self.undo.back_mut() else { return };
+        let index = self.index.get_or_insert_with(|| {
+            entry.changes.iter().enumerate().map(|(pos, c)| (c.idx, pos)).collect()
+        });
+        match index.get(&change.idx) {
+            Some(&pos) => entry.changes[pos].new = change.new,
+            None => {
+                index.insert(change.idx, entry.changes.len());
+                entry.changes.push(change);
+                self.bytes += std::mem::size_of::<CellChange>();
+            }
+        }
+        if !self.redo.is_empty() {
+            self.bytes -= self.redo.drain(..).map(|e| e.bytes()).sum::<usize>();
+        }
+        self.enforce_limit();
+    }
+
+    /// Pop the newest edit, returning the changes to revert.
+    pub fn undo(&mut self) -> Option<&[CellChange]> {
+        self.recording = false;
+        self.index = None;
+        let entry = self.undo.pop_back()?;
+        self.redo.push(entry);
+        self.redo.last().map(|e| e.changes.as_slice())
+    }
+
+    /// Re-apply the newest undone edit, returning its changes.
+    pub fn redo(&mut self) -> Option<&[CellChange]> {
+        self.recording = false;
+        self.index = None;
+        let entry = self.redo.pop()?;
+        self.undo.push_back(entry);
+        self.undo.back().map(|e| e.changes.as_slice())
+    }
+
+    fn enforce_limit(&mut self) {
+        while self.bytes > self.limit && !self.redo.is_empty() {
+            let entry = self.redo.remove(0);
+            self.bytes -= entry.bytes();
+        }
+        while self.bytes > self.limit && self.undo.len() > 1 {
+            if let Some(entry) = self.undo.pop_front() {
+                self.bytes -= entry.bytes();
+            }
+        }
+    }
+}
@@ -1,22 +1,91 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 use rand::Rng;
-
-/// Parameters controlling how cells gain or lose brightness.
-/// They can be tweaked while the simulation is running to explore different
-/// ecological dynamics without recompiling.
-
-#[wasm_bindgen]
-#[derive(Copy, Clone, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+mod activity;
+mod ages;
+mod animation;
+mod bitgrid;
+mod brush;
+mod compress;
+mod cycles;
+mod hashlife;
+mod history;
+mod image;
+mod import;
+mod journal;
+mod palette;
+mod patterns;
+mod presets;
+mod render;
+mod rng;
+mod rule;
+mod savefile;
+mod stamp;
+mod tiles;
+mod trail;
+
+pub use animation::{AnimationFormat, AnimationOptions};
+pub use bitgrid::BitGrid;
+pub use brush::{BrushSettings, BrushShape};
+pub use hashlife::HashLife;
+pub use image::{Resample, RgbaImage};
+pub use import::{Dither, ImportOptions, Placement};
+pub use patterns::{
+    detect_format, parse_life105, parse_life106, parse_macrocell, parse_pattern, parse_plaintext, parse_rle,
+    write_life105, write_life106, write_macrocell, write_pattern, write_plaintext, write_rle, Pattern, PatternFormat,
+};
+pub use palette::{Palette, PALETTE_NAMES};
+pub use presets::{preset, Preset, PRESETS};
+pub use render::RenderSource;
+pub use stamp::{BlendMode, StampOptions};
+pub use rule::{format_rule, parse_rule};
+pub use tiles::TILE_SIZE;
+pub use trail::TrailMode;
+
+use activity::ActivityLayer;
+use ages::AgeLayer;
+use cycles::CycleDetector;
+use history::{CellChange, History};
+use journal::{EditJournal, EditKind};
+use rng::SplitMix64;
+use render::{Framebuffer, Layers};
+use savefile::{Layer, SaveData, LAYER_ACTIVITY, LAYER_AGES, LAYER_DRAWN};
+use tiles::{ActiveTiles, StatsLevel, TileStats};
+use trail::TrailBuffer;
+
+/// Bytes appended to legacy raw saves: the RNG state and seed.
+const RNG_STATE_LEN: usize = 16;
+
+/// The channel of an `Individual` that decides whether it is alive.
+/// Serialized in lowercase; capitalised names are accepted too.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LifeChannel {
+    #[serde(alias = "Hue")]
     Hue,
+    #[serde(alias = "Saturation")]
     Saturation,
+    #[serde(alias = "Luminance")]
     Luminance,
+    #[serde(alias = "Alpha")]
     Alpha,
 }
 
-#[wasm_bindgen]
-#[derive(Clone, Copy, Debug)]
+/// Parameters controlling how cells gain or lose brightness.
+/// They can be tweaked while the simulation is running to explore different
+/// ecological dynamics without recompiling.
+///
+/// In JSON, missing fields take their default and `rule` may be given in
+/// `B3/S23` notation.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LifeParams {
+    #[serde(deserialize_with = "rule::deserialize_rule")]
     pub rule: u32,
     pub decay_step: u8,
     pub recovery_step: u8,
@@ -30,7 +99,19 @@ pub struct LifeParams {
     pub life_channel: LifeChannel,
 }
 
-#[wasm_bindgen]
+/// Which statistics `tick` gathers.  Histograms and medians are the costly
+/// part; `Basic` keeps averages and population counts only.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StatsMode {
+    Off,
+    Basic,
+    Full,
+    /// Full statistics every `stats_interval` generations.
+    EveryN,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Debug, Clone)]
 pub struct UniverseStats {
     avg_hue: f32,
@@ -44,30 +125,37 @@ pub struct UniverseStats {
     alive_count: usize,
     dead_count: usize,
     population_ratio: f32,
+    mean_age: f32,
+    max_age: u32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl UniverseStats {
-    #[wasm_bindgen(getter)] pub fn avg_hue(&self) -> f32 { self.avg_hue }
-    #[wasm_bindgen(getter)] pub fn median_hue(&self) -> f32 { self.median_hue }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn avg_hue(&self) -> f32 { self.avg_hue }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn median_hue(&self) -> f32 { self.median_hue }
 
-    #[wasm_bindgen(getter)] pub fn avg_saturation(&self) -> f32 { self.avg_saturation }
-    #[wasm_bindgen(getter)] pub fn median_saturation(&self) -> f32 { self.median_saturation }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn avg_saturation(&self) -> f32 { self.avg_saturation }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn median_saturation(&self) -> f32 { self.median_saturation }
 
-    #[wasm_bindgen(getter)] pub fn avg_luminance(&self) -> f32 { self.avg_luminance }
-    #[wasm_bindgen(getter)] pub fn median_luminance(&self) -> f32 { self.median_luminance }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn avg_luminance(&self) -> f32 { self.avg_luminance }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn median_luminance(&self) -> f32 { self.median_luminance }
 
-    #[wasm_bindgen(getter)] pub fn avg_alpha(&self) -> f32 { self.avg_alpha }
-    #[wasm_bindgen(getter)] pub fn median_alpha(&self) -> f32 { self.median_alpha }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn avg_alpha(&self) -> f32 { self.avg_alpha }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn median_alpha(&self) -> f32 { self.median_alpha }
 
-    #[wasm_bindgen(getter)] pub fn alive_count(&self) -> usize { self.alive_count }
-    #[wasm_bindgen(getter)] pub fn dead_count(&self) -> usize { self.dead_count }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn alive_count(&self) -> usize { self.alive_count }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn dead_count(&self) -> usize { self.dead_count }
 
-    #[wasm_bindgen(getter)] pub fn population_ratio(&self) -> f32 { self.population_ratio }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn population_ratio(&self) -> f32 { self.population_ratio }
+
+    /// Mean and maximum generations since birth of the live cells; 0
+    /// unless age tracking is on.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn mean_age(&self) -> f32 { self.mean_age }
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))] pub fn max_age(&self) -> u32 { self.max_age }
 }
 
 /// The simulation universe.  The `params` field makes the life‑cycle tunable at runtime.
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Universe {
     width: u32,
     height: u32,
@@ -77,9 +165,28 @@ pub struct Universe {
     params: LifeParams,
     stats: UniverseStats,
     brush_state: BrushState,
+    binary: Option<BitGrid>,
+    /// Cells the last binary sync flipped, whose previous values `next`
+    /// still holds.
+    flipped: Vec<usize>,
+    tiles: ActiveTiles,
+    seed: u64,
+    rng: SplitMix64,
+    generation: u64,
+    stats_mode: StatsMode,
+    stats_interval: u32,
+    history: History,
+    journal: EditJournal,
+    cycles: CycleDetector,
+    pattern_color: Option<Individual>,
+    render: Framebuffer,
+    ages: AgeLayer,
+    activity: ActivityLayer,
+    trail: TrailBuffer,
 }
 
 impl LifeParams {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         rule: u32,
         decay_step: u8,
@@ -109,6 +216,19 @@ impl LifeParams {
     }
 }
 
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl LifeParams {
+    /// These params as JSON, with `rule` as a number.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("LifeParams always serializes")
+    }
+
+    /// Params from JSON; missing fields take their default.
+    pub fn from_json(json: &str) -> Result<LifeParams, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid LifeParams JSON: {}", e))
+    }
+}
+
 impl Default for LifeParams {
     fn default() -> Self {
         Self::new(770, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha)
@@ -125,6 +245,29 @@ pub struct Individual {
 }
 
 impl Individual {
+    /// The pixel this cell renders as.  `use_alpha` keeps its alpha instead
+    /// of rendering opaque; `use_rgb` shows the raw channels as RGB.
+    pub fn to_rgba(&self, use_alpha: bool, use_rgb: bool) -> [u8; 4] {
+        let a = if use_alpha { self.alpha } else { 255 };
+        if use_rgb {
+            return [self.hue, self.saturation, self.luminance, a];
+        }
+        let (r, g, b) = hsl_to_rgb(
+            self.hue as f32 / 255.0,
+            self.saturation as f32 / 255.0,
+            self.luminance as f32 / 255.0,
+        );
+        let byte = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        [byte(r), byte(g), byte(b), a]
+    }
+
+    /// The cell showing an RGBA colour, the inverse of `to_rgba`.
+    pub fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Individual {
+        let (h, s, l) = rgb_to_hsl(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+        let byte = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        Individual { hue: byte(h), saturation: byte(s), luminance: byte(l), alpha: a }
+    }
+
     pub fn activity_value(&self, channel: LifeChannel) -> u8 {
         match channel {
             LifeChannel::Hue => self.hue,
@@ -158,17 +301,53 @@ pub fn rgb_to_hsl(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
     (h, s, l)
 }
 
+/// Inverse of `rgb_to_hsl`; all components in `0.0..=1.0`.  Matches the
+/// client's render shader.
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (f32, f32, f32) {
+    if s == 0.0 {
+        return (l, l, l);
+    }
+    let f2 = if l < 0.5 { l * (1.0 + s) } else { l + s - s * l };
+    let f1 = 2.0 * l - f2;
+    let channel = |t: f32| {
+        let t = t.rem_euclid(1.0);
+        if 6.0 * t < 1.0 {
+            f1 + (f2 - f1) * 6.0 * t
+        } else if 2.0 * t < 1.0 {
+            f2
+        } else if 3.0 * t < 2.0 {
+            f1 + (f2 - f1) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            f1
+        }
+    };
+    (channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0))
+}
+
+/// The colour given to cells born in the binary engines, which carry no
+/// colour of their own: white at full strength on the life channel.
+pub(crate) fn binary_born_cell(channel: LifeChannel) -> Individual {
+    let mut born = Individual { hue: 0, saturation: 0, luminance: 255, alpha: 255 };
+    match channel {
+        LifeChannel::Hue => born.hue = 255,
+        LifeChannel::Saturation => born.saturation = 255,
+        LifeChannel::Luminance | LifeChannel::Alpha => {}
+    }
+    born
+}
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct BrushState {
     pub last_id: Option<u64>,
     pub points: Vec<(u32, u32)>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Universe {
-  #[wasm_bindgen(constructor)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
   pub fn new(width: u32, height: u32) -> Self {
       let size = (width * height) as usize;
+      let seed = rand::random::<u64>();
       Self {
           width,
           height,
@@ -188,8 +367,26 @@ impl Universe {
               alive_count: 0,
               dead_count: 0,
               population_ratio: 0.0,
+              mean_age: 0.0,
+              max_age: 0,
           },
           brush_state: BrushState::default(),
+          binary: None,
+          flipped: Vec::new(),
+          tiles: ActiveTiles::new(width, height),
+          seed,
+          rng: SplitMix64::new(seed),
+          generation: 0,
+          stats_mode: StatsMode::Full,
+          stats_interval: 1,
+          history: History::default(),
+          journal: EditJournal::default(),
+          cycles: CycleDetector::default(),
+          pattern_color: None,
+          render: Framebuffer::new(),
+          ages: AgeLayer::default(),
+          activity: ActivityLayer::default(),
+          trail: TrailBuffer::default(),
       }
   }
 
@@ -199,11 +396,52 @@ impl Universe {
       Self { params, ..Self::new(width, height) }
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn stats(&self) -> UniverseStats {
       self.stats.clone()
   }
 
+  /// Reseed the generator behind `randomize` and hue drift.  Two universes
+  /// with the same seed, params and edits evolve identically.
+  pub fn set_seed(&mut self, seed: u64) {
+      self.seed = seed;
+      self.rng = SplitMix64::new(seed);
+  }
+
+  /// The seed most recently passed to `set_seed`, or the random seed the
+  /// universe was created with.
+  pub fn seed(&self) -> u64 {
+      self.seed
+  }
+
+  /// Switch `tick` to the bit-packed engine.  Only the birth/survival rule is
+  /// applied: surviving cells keep their colour, dying cells are cleared and
+  /// newborn cells are drawn at full strength.
+  pub fn set_binary_mode(&mut self, enabled: bool) {
+      self.binary = if enabled {
+          Some(BitGrid::new(self.width, self.height, self.params.rule))
+      } else {
+          None
+      };
+      self.reload_binary();
+      self.tiles.mark_all();
+  }
+
+  pub fn binary_mode(&self) -> bool {
+      self.binary.is_some()
+  }
+
+  /// Number of tiles the next `tick` will recompute; the rest are skipped
+  /// because nothing in or around them changed last generation.
+  pub fn active_tiles(&self) -> usize {
+      self.tiles.active_count()
+  }
+
+  pub fn tile_count(&self) -> usize {
+      self.tiles.len()
+  }
+
+  #[allow(clippy::too_many_arguments)]
   pub fn set_params(
       &mut self,
       rule: u32,
@@ -218,7 +456,7 @@ impl Universe {
       hue_lerp_factor: f32,
       life_channel: LifeChannel,
   ) {
-      self.params = LifeParams::new(
+      self.apply_params(LifeParams::new(
           rule,
           decay_step,
           recovery_step,
@@ -230,7 +468,33 @@ impl Universe {
           hue_drift_strength,
           hue_lerp_factor,
           life_channel,
-      );
+      ));
+  }
+
+  /// Current params as JSON.
+  pub fn params_json(&self) -> String {
+      self.params.to_json()
+  }
+
+  /// Replace the params from JSON; missing fields take their default.
+  pub fn set_params_json(&mut self, json: &str) -> Result<(), String> {
+      self.apply_params(LifeParams::from_json(json)?);
+      Ok(())
+  }
+
+  /// Every built-in preset as a JSON array of
+  /// `{name, description, params, seed}`.
+  pub fn presets_json() -> String {
+      serde_json::to_string(PRESETS).expect("presets always serialize")
+  }
+
+  /// Apply the params of the built-in preset `name` and reseed with its
+  /// recommended seed, ready for `randomize`.
+  pub fn apply_preset(&mut self, name: &str) -> Result<(), String> {
+      let preset = preset(name).ok_or_else(|| format!("unknown preset '{}'", name))?;
+      self.apply_params(preset.params);
+      self.set_seed(preset.seed);
+      Ok(())
   }
 
   #[inline]
@@ -265,11 +529,18 @@ impl Universe {
         alpha: t,
       };
     }
+    self.reload_binary();
+    self.tiles.mark_all();
+    self.cycles.invalidate();
+    self.history.clear();
+    self.journal.clear();
+    self.ages.reset(&self.cells, self.params.life_channel);
+    self.activity.reset(self.cells.len());
   }
 
 
   pub fn randomize(&mut self) {
-    let mut rng = rand::rng();
+    let rng = &mut self.rng;
     for cell in self.cells.iter_mut() {
       if rng.random_bool(0.5) {
         *cell = Individual {
@@ -282,6 +553,13 @@ impl Universe {
         *cell = Individual::default();
       }
     }
+    self.reload_binary();
+    self.tiles.mark_all();
+    self.cycles.invalidate();
+    self.history.clear();
+    self.journal.clear();
+    self.ages.reset(&self.cells, self.params.life_channel);
+    self.activity.reset(self.cells.len());
   }
 
   pub fn clear(&mut self) {
@@ -293,53 +571,51 @@ impl Universe {
         alpha: 0,
       };
     }
+    self.reload_binary();
+    self.tiles.mark_all();
+    self.cycles.invalidate();
+    self.history.clear();
+    self.journal.clear();
+    self.ages.reset(&self.cells, self.params.life_channel);
+    self.activity.reset(self.cells.len());
   }
 
-  #[wasm_bindgen]
+  /// Paint a `stamp_w` x `stamp_h` RGBA stamp centred at `(x, y)`, mixing
+  /// each pixel's colour in by its alpha.
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
   pub fn draw_stamp_at(&mut self, x: u32, y: u32, stamp_w: u32, stamp_h: u32, data: &[u8]) {
-      for sy in 0..stamp_h {
-          let uy = y + sy - stamp_h / 2;
-          if uy >= self.height { continue; }
-
-          for sx in 0..stamp_w {
-              let ux = x + sx - stamp_w / 2;
-              if ux >= self.width { continue; }
-
-              let i = ((sy * stamp_w + sx) * 4) as usize;
-              let r = data[i] as f32 / 255.0;
-              let g = data[i + 1] as f32 / 255.0;
-              let b = data[i + 2] as f32 / 255.0;
-              let a = data[i + 3] as f32 / 255.0;
-
-              if a <= 0.001 { continue; }
-
-              let (h, s, l) = rgb_to_hsl(r, g, b);
-              let target_h = (h * 255.0).round() as u8;
-              let target_s = (s * 255.0).round() as u8;
-              let target_l = (l * 255.0).round() as u8;
-
-              let idx = self.index(uy, ux);
-              let old = self.cells[idx];
-
-              let (new_h, new_s, new_l) = if a >= 0.999 {
-                  (target_h, target_s, target_l)
-              } else {
-                  let lerp = |a: u8, b: u8, t: f32| -> u8 {
-                      ((a as f32 * (1.0 - t)) + (b as f32 * t)).round() as u8
-                  };
-                  (
-                      lerp(old.hue, target_h, a),
-                      lerp(old.saturation, target_s, a),
-                      lerp(old.luminance, target_l, a),
-                  )
-              };
+      self.draw_stamp(x, y, stamp_w, stamp_h, data, &StampOptions::default());
+  }
 
-              self.set_cell(uy, ux, new_h, new_s, new_l, 255);
-          }
+  /// `draw_stamp_at` rotated, scaled, flipped, wrapped and blended per
+  /// `options`, as one undoable edit.
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn draw_stamp(&mut self, x: u32, y: u32, stamp_w: u32, stamp_h: u32, data: &[u8], options: &StampOptions) {
+      if self.width == 0 || self.height == 0 {
+          return;
+      }
+      let channel = self.params.life_channel;
+      self.journal.begin(EditKind::Stamp);
+      for (dx, dy, pixel) in options.footprint(stamp_w, stamp_h, data) {
+          let (ux, uy) = (x as i64 + dx, y as i64 + dy);
+          let (ux, uy) = if options.wrap {
+              (ux.rem_euclid(self.width as i64), uy.rem_euclid(self.height as i64))
+          } else if (0..self.width as i64).contains(&ux) && (0..self.height as i64).contains(&uy) {
+              (ux, uy)
+          } else {
+              continue;
+          };
+          let old = self.cells[self.index(uy as u32, ux as u32)];
+          let new = stamp::blend(old, pixel, options.blend, channel);
+          self.set_cell(uy as u32, ux as u32, new.hue, new.saturation, new.luminance, new.alpha);
       }
+      self.journal.end();
   }
 
-  #[wasm_bindgen]
+  /// Paint a hard circular dab, or erase one unless `add_mode`; see
+  /// `draw_brush_with`.
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  #[allow(clippy::too_many_arguments)]
   pub fn draw_brush(
       &mut self,
       cx: u32,
@@ -351,59 +627,30 @@ impl Universe {
       l: u8,
       brush_id: u64,
   ) {
-      const MAX_POINTS: usize = 6;
-
-      // Function to draw a brush dab at a point
-      let draw_circle = |universe: &mut Universe, x: u32, y: u32| {
-          let r2 = (radius * radius) as i32;
-          for dy in -(radius as i32)..=(radius as i32) {
-              let y = y as i32 + dy;
-              if y < 0 || y >= universe.height as i32 {
-                  continue;
-              }
-              let dy2 = dy * dy;
-              for dx in -(radius as i32)..=(radius as i32) {
-                  let dx2 = dx * dx;
-                  if dx2 + dy2 > r2 {
-                      continue;
-                  }
-                  let x = x as i32 + dx;
-                  if x < 0 || x >= universe.width as i32 {
-                      continue;
-                  }
-                  let (hue, sat, lum) = if add_mode { (h, s, l) } else { (0, 0, 0) };
-                  universe.set_cell(y as u32, x as u32, hue, sat, lum, 255);
-              }
-          }
-      };
-
-      // Function to interpolate and draw a segment using Catmull-Rom
-      let draw_catmull_rom = |universe: &mut Universe, p0: (u32, u32), p1: (u32, u32), p2: (u32, u32), p3: (u32, u32)| {
-          let steps = 256;
-          for i in 0..=steps {
-              let t = i as f32 / steps as f32;
-              let t2 = t * t;
-              let t3 = t2 * t;
-
-              let blend = |a: u32, b: u32, c: u32, d: u32| -> u32 {
-                  let a = a as f32;
-                  let b = b as f32;
-                  let c = c as f32;
-                  let d = d as f32;
-                  let result = 0.5 * (
-                      (2.0 * b) +
-                      (-a + c) * t +
-                      (2.0 * a - 5.0 * b + 4.0 * c - d) * t2 +
-                      (-a + 3.0 * b - 3.0 * c + d) * t3
-                  );
-                  result.round().max(0.0) as u32
-              };
+      let settings = BrushSettings::new(BrushShape::Circle, radius);
+      self.draw_brush_with(cx, cy, add_mode, h, s, l, brush_id, &settings);
+  }
 
-              let x = blend(p0.0, p1.0, p2.0, p3.0);
-              let y = blend(p0.1, p1.1, p2.1, p3.1);
-              draw_circle(universe, x, y);
-          }
-      };
+  /// Paint a dab shaped by `settings` at `(cx, cy)`, or erase one unless
+  /// `add_mode`.  Successive dabs with the same `brush_id` are joined by a
+  /// spline into one undoable stroke.  Each call paints only the spline's
+  /// newest segment: over a stroke that covers the same cells as repainting
+  /// every recent segment each time, which is what `draw_brush` did before
+  /// brush settings, while painting soft or sprayed cells once.
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  #[allow(clippy::too_many_arguments)]
+  pub fn draw_brush_with(
+      &mut self,
+      cx: u32,
+      cy: u32,
+      add_mode: bool,
+      h: u8,
+      s: u8,
+      l: u8,
+      brush_id: u64,
+      settings: &BrushSettings,
+  ) {
+      const MAX_POINTS: usize = 6;
 
       // Manage the brush stroke state
       if self.brush_state.last_id == Some(brush_id) {
@@ -417,21 +664,168 @@ impl Universe {
           self.brush_state.last_id = Some(brush_id);
       }
 
-      // Draw with spline if we have enough points
-      if self.brush_state.points.len() >= 4 {
-          let points = self.brush_state.points.clone(); // Take a copy to satisfy borrow checker
-          for i in 0..(points.len() - 3) {
-              draw_catmull_rom(
-                  self,
-                  points[i],
-                  points[i + 1],
-                  points[i + 2],
-                  points[i + 3],
-              );
-          }
+      // Dab along a Catmull-Rom spline through the newest segment if we
+      // have enough points; earlier segments were painted by earlier calls
+      let points = &self.brush_state.points;
+      let dabs: Vec<(u32, u32)> = if points.len() >= 4 {
+          let [p0, p1, p2, p3] = [0, 1, 2, 3].map(|k| points[points.len() - 4 + k]);
+          let steps = 256;
+          (0..=steps)
+              .map(|i| {
+                  let t = i as f32 / steps as f32;
+                  let t2 = t * t;
+                  let t3 = t2 * t;
+
+                  let blend = |a: u32, b: u32, c: u32, d: u32| -> u32 {
+                      let a = a as f32;
+                      let b = b as f32;
+                      let c = c as f32;
+                      let d = d as f32;
+                      let result = 0.5 * (
+                          (2.0 * b) +
+                          (-a + c) * t +
+                          (2.0 * a - 5.0 * b + 4.0 * c - d) * t2 +
+                          (-a + 3.0 * b - 3.0 * c + d) * t3
+                      );
+                      result.round().max(0.0) as u32
+                  };
+
+                  (blend(p0.0, p1.0, p2.0, p3.0), blend(p0.1, p1.1, p2.1, p3.1))
+              })
+              .collect()
       } else {
-          draw_circle(self, cx, cy); // Fallback for initial points
+          vec![(cx, cy)] // Fallback for initial points
+      };
+
+      // Each cell takes its strongest coverage from any dab, so overlapping
+      // dabs don't compound soft edges or spray
+      let mut coverage: BTreeMap<usize, f32> = BTreeMap::new();
+      let r = settings.radius as i32;
+      for (x, y) in dabs {
+          for dy in -r..=r {
+              let y = y as i32 + dy;
+              if y < 0 || y >= self.height as i32 {
+                  continue;
+              }
+              for dx in -r..=r {
+                  let x = x as i32 + dx;
+                  if x < 0 || x >= self.width as i32 {
+                      continue;
+                  }
+                  let weight = settings.weight(dx, dy);
+                  if weight > 0.0 {
+                      let entry = coverage.entry(self.index(y as u32, x as u32)).or_insert(0.0);
+                      *entry = entry.max(weight);
+                  }
+              }
+          }
       }
+
+      // Spray and jitter are seeded by the dab, not the simulation's RNG,
+      // so painting never changes how a run unfolds
+      let mut rng = SplitMix64::new(brush_id.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ ((cx as u64) << 32 | cy as u64));
+      self.journal.begin(EditKind::Stroke(brush_id));
+      for (idx, weight) in coverage {
+          if settings.density < 1.0 && rng.random::<f32>() >= settings.density {
+              continue;
+          }
+          let jitter = settings.hue_jitter as i32;
+          let hue = if jitter > 0 { h.wrapping_add(rng.random_range(-jitter..=jitter) as u8) } else { h };
+          let target = if add_mode {
+              Individual { hue, saturation: s, luminance: l, alpha: 255 }
+          } else {
+              Individual { hue: 0, saturation: 0, luminance: 0, alpha: 255 }
+          };
+          let old = self.cells[idx];
+          let lerp = |a: u8, b: u8| (a as f32 * (1.0 - weight) + b as f32 * weight).round() as u8;
+          let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+          self.set_cell(
+              row,
+              col,
+              lerp(old.hue, target.hue),
+              lerp(old.saturation, target.saturation),
+              lerp(old.luminance, target.luminance),
+              lerp(old.alpha, target.alpha),
+          );
+      }
+      self.journal.end();
+  }
+
+  /// Revert the newest brush stroke or stamp.  Restored cells are drawn like
+  /// any other edit.  Returns false if there is nothing to undo.
+  pub fn undo(&mut self) -> bool {
+      let Some(changes) = self.journal.undo() else { return false };
+      let changes = changes.to_vec();
+      for change in changes.iter().rev() {
+          self.write_cell(change.idx as usize, change.old);
+      }
+      true
+  }
+
+  /// Re-apply the newest undone stroke or stamp.
+  pub fn redo(&mut self) -> bool {
+      let Some(changes) = self.journal.redo() else { return false };
+      let changes = changes.to_vec();
+      for change in &changes {
+          self.write_cell(change.idx as usize, change.new);
+      }
+      true
+  }
+
+  pub fn can_undo(&self) -> bool {
+      self.journal.undo_len() > 0
+  }
+
+  pub fn can_redo(&self) -> bool {
+      self.journal.redo_len() > 0
+  }
+
+  /// Cap the undo journal at `bytes`, dropping the oldest edits beyond it.
+  /// `0` disables undo.
+  pub fn set_undo_limit(&mut self, bytes: usize) {
+      self.journal.set_limit(bytes);
+  }
+
+  pub fn undo_limit(&self) -> usize {
+      self.journal.limit()
+  }
+
+  pub fn clear_undo(&mut self) {
+      self.journal.clear();
+  }
+
+  /// Colour given to live cells of loaded patterns.  Until set, patterns are
+  /// drawn white at full strength on the life channel.
+  pub fn set_pattern_color(&mut self, h: u8, s: u8, l: u8, a: u8) {
+      self.pattern_color = Some(Individual { hue: h, saturation: s, luminance: l, alpha: a });
+  }
+
+  /// Load an RLE pattern with its top-left corner at `(x, y)`, replacing the
+  /// cells of its bounding box.  A `rule` header that parses is applied to
+  /// `params.rule`.
+  pub fn load_rle(&mut self, text: &str, x: u32, y: u32) -> Result<(), String> {
+      let pattern = parse_rle(text)?;
+      self.load_parsed(&pattern, x, y);
+      Ok(())
+  }
+
+  /// Write the `w` x `h` region at `(x, y)` as RLE, treating cells with a
+  /// non-zero life channel as live.
+  pub fn export_rle(&self, x: u32, y: u32, w: u32, h: u32) -> String {
+      write_rle(&self.extract_pattern(x, y, w, h))
+  }
+
+  /// Load a pattern in any supported format, detected from its header, as
+  /// `load_rle` does.
+  pub fn load_pattern(&mut self, text: &str, x: u32, y: u32) -> Result<(), String> {
+      let pattern = parse_pattern(text)?;
+      self.load_parsed(&pattern, x, y);
+      Ok(())
+  }
+
+  /// Write the `w` x `h` region at `(x, y)` in `format`.
+  pub fn export_pattern(&self, x: u32, y: u32, w: u32, h: u32, format: PatternFormat) -> String {
+      write_pattern(&self.extract_pattern(x, y, w, h), format)
   }
   /// Advance the automaton by one generation.
   ///
@@ -443,184 +837,197 @@ impl Universe {
   /// so classic Conway "B3/S23" is
   /// `0b0000001_00000100_00001000u32  // 0x0408`
   pub fn tick(&mut self) {
-      if self.width == 0 || self.height == 0 {
-          return;
+      let level = match self.stats_mode {
+          StatsMode::Off => StatsLevel::None,
+          StatsMode::Basic => StatsLevel::Basic,
+          StatsMode::Full => StatsLevel::Full,
+          StatsMode::EveryN if (self.generation + 1).is_multiple_of(self.stats_interval as u64) => {
+              StatsLevel::Full
+          }
+          StatsMode::EveryN => StatsLevel::None,
+      };
+      self.step(level);
+      self.sync_binary();
+  }
+
+  /// Advance `n` generations in one call.  Statistics are gathered for the
+  /// final generation only (none at all with `StatsMode::Off`).
+  pub fn tick_n(&mut self, n: u32) {
+      let last = match self.stats_mode {
+          StatsMode::Off => StatsLevel::None,
+          StatsMode::Basic => StatsLevel::Basic,
+          StatsMode::Full | StatsMode::EveryN => StatsLevel::Full,
+      };
+      for i in 0..n {
+          self.step(if i + 1 == n { last } else { StatsLevel::None });
       }
+      self.sync_binary();
+  }
 
-      use std::f32::consts::{E, TAU};
+  /// Which statistics `tick` gathers.
+  pub fn set_stats_mode(&mut self, mode: StatsMode) {
+      self.stats_mode = mode;
+  }
 
-      let LifeParams {
-          rule,
-          decay_step,
-          recovery_step,
-          sat_recovery_factor,
-          sat_decay_factor,
-          lum_decay_factor,
-          life_decay_factor,
-          sat_ghost_factor,
-          hue_drift_strength,
-          hue_lerp_factor,
-          life_channel,
-      } = self.params;
-
-      // Pre-calculate constants used in the loop
-      let decay_step_f = decay_step as f32;
-      let sat_decay_term = decay_step_f * E * sat_decay_factor;
-      let lum_decay_term = decay_step_f * E * lum_decay_factor;
-      let life_decay_term = decay_step_f * E * life_decay_factor;
-      let sat_recovery = recovery_step as f32 * sat_recovery_factor;
-      let tau_div_255 = TAU / 255.0;
-      let total_size = (self.width * self.height) as usize;
+  pub fn stats_mode(&self) -> StatsMode {
+      self.stats_mode
+  }
 
-      let mut sum_hue = 0u32;
-      let mut sum_sat = 0u32;
-      let mut sum_lum = 0u32;
-      let mut sum_life = 0u32;
-      let mut alive_count = 0usize;
+  /// Generation interval used by `StatsMode::EveryN`.
+  pub fn set_stats_interval(&mut self, interval: u32) {
+      self.stats_interval = interval.max(1);
+  }
 
-      let mut histogram_sat = [0usize; 256];
-      let mut histogram_lum = [0usize; 256];
-      let mut histogram_hue = [0usize; 256];
-      let mut histogram_life = [0usize; 256];
+  pub fn stats_interval(&self) -> u32 {
+      self.stats_interval
+  }
 
-      // Pre-allocate neighbors array to avoid repeated allocations
-      let mut neighbors = [0usize; 8];
+  /// Number of generations advanced since the universe was created.
+  pub fn generation(&self) -> u64 {
+      self.generation
+  }
 
-      // Process cells in chunks for better cache utilization
-      let chunk_size = 64; // Adjust based on cache line size
-      for chunk_start in (0..total_size).step_by(chunk_size) {
-          let chunk_end = (chunk_start + chunk_size).min(total_size);
+  /// Hash the state after every generation to detect cycles, extinction and
+  /// stagnation.  Costs a pass over the tiles that changed each generation.
+  pub fn set_cycle_detection(&mut self, enabled: bool) {
+      self.cycles.enabled = enabled;
+      self.cycles.invalidate();
+  }
 
-          for idx in chunk_start..chunk_end {
-              if std::mem::take(&mut self.draw_buffer[idx]) {
-                  self.next[idx] = self.cells[idx];
-                  continue;
-              }
+  pub fn cycle_detection(&self) -> bool {
+      self.cycles.enabled
+  }
 
-              let row = (idx / self.width as usize) as u32;
-              let col = (idx % self.width as usize) as u32;
-              let cell = self.cells[idx];
-
-              let mut live_neighbors = 0u8;
-              let mut sat_sum = 0.0f32;
-              let mut lum_sum = 0.0f32;
-              let mut alpha_sum = 0.0f32;
-              let mut strongest_hue = 0u8;
-              let mut max_lum = 0u8;
-              let mut sin_sum = 0.0f32;
-              let mut cos_sum = 0.0f32;
-
-              // Get neighbors indices
-              self.get_neighbour_indices(row, col, &mut neighbors);
-
-              // Single pass through neighbors for all calculations
-              for &nidx in &neighbors {
-                  let n = self.cells[nidx];
-                  if n.activity_value(life_channel) > 0 {
-                      live_neighbors += 1;
-                      sat_sum += n.saturation as f32;
-                      lum_sum += n.luminance as f32;
-                      alpha_sum += n.alpha as f32;
-
-                      if n.luminance > max_lum {
-                          max_lum = n.luminance;
-                          strongest_hue = n.hue;
-                      }
-                      let angle = (n.hue as f32) * tau_div_255;
-                      sin_sum += angle.sin();
-                      cos_sum += angle.cos();
-                  }
-              }
+  /// Hash all four channels instead of just the life channel, so only exact
+  /// repeats of the colours count as cycles.
+  pub fn set_cycle_hash_full(&mut self, full: bool) {
+      self.cycles.full = full;
+      self.cycles.invalidate();
+  }
 
-              let bit_index = live_neighbors as u32
-                  + if cell.activity_value(life_channel) > 0 { 9 } else { 0 };
-              let next_alive = ((rule >> bit_index) & 1) == 1;
+  pub fn cycle_hash_full(&self) -> bool {
+      self.cycles.full
+  }
 
-              let next_cell = match (cell.activity_value(life_channel) > 0, next_alive) {
-                  (true, true) => {
-                      let new_sat = (cell.saturation as f32 + sat_recovery).min(255.0);
-                      Individual { saturation: new_sat as u8, ..cell }
-                  }
-                  (true, false) => {
-                      Individual {
-                          hue: cell.hue,
-                          saturation: (cell.saturation as f32 - sat_decay_term).max(0.0) as u8,
-                          luminance: (cell.luminance as f32 - lum_decay_term).max(0.0) as u8,
-                          alpha: (cell.alpha as f32 - life_decay_term).max(0.0) as u8,
-                      }
-                  }
-                  (false, true) => {
-                      let avg = |sum: f32| {
-                          if live_neighbors == 0 {
-                              0
-                          } else {
-                              (sum / live_neighbors as f32).round() as u8
-                          }
-                      };
-
-                      let mean_angle = if sin_sum == 0.0 && cos_sum == 0.0 {
-                          0.0
-                      } else {
-                          sin_sum.atan2(cos_sum)
-                      };
-
-                      let strongest_angle = (strongest_hue as f32) * tau_div_255;
-                      let mixed_angle = (1.0 - hue_lerp_factor) * mean_angle
-                          + hue_lerp_factor * strongest_angle;
-                      let drift = (rand::random::<f32>() - 0.5) * hue_drift_strength * 2.0;
-                      let final_angle = (mixed_angle + drift).rem_euclid(TAU);
-                      let hue = ((final_angle / TAU) * 255.0).round() as u8;
-
-                      let v = |ch, fallback| if life_channel == ch { 255 } else { fallback };
-
-                      Individual {
-                          hue:        v(LifeChannel::Hue,        hue),
-                          saturation: v(LifeChannel::Saturation, avg(sat_sum).saturating_add(1)),
-                          luminance:  v(LifeChannel::Luminance,  avg(lum_sum).saturating_add(1)),
-                          alpha:      v(LifeChannel::Alpha,      avg(alpha_sum).saturating_add(1)),
-                      }
-                  }
-                  _ => Individual {
-                      hue: cell.hue,
-                      saturation: (cell.saturation as f32 * sat_ghost_factor) as u8,
-                      luminance: (cell.luminance as f32 * lum_decay_factor) as u8,
-                      alpha: (cell.alpha as f32 * life_decay_factor) as u8,
-                  },
-              };
+  /// Number of recent generations compared against; bounds the longest
+  /// detectable period.
+  pub fn set_cycle_window(&mut self, generations: u32) {
+      self.cycles.set_window(generations as usize);
+  }
 
-              self.next[idx] = next_cell;
+  pub fn cycle_window(&self) -> u32 {
+      self.cycles.window() as u32
+  }
 
-              // Update statistics
-              sum_hue += next_cell.hue as u32;
-              sum_sat += next_cell.saturation as u32;
-              sum_lum += next_cell.luminance as u32;
-              sum_life += next_cell.alpha as u32;
-              histogram_hue[next_cell.hue as usize] += 1;
-              histogram_sat[next_cell.saturation as usize] += 1;
-              histogram_lum[next_cell.luminance as usize] += 1;
-              histogram_life[next_cell.alpha as usize] += 1;
+  /// Period of the cycle the universe is in, if the current state was seen
+  /// within the window.  Still lifes have period 1.
+  pub fn detected_period(&self) -> Option<u64> {
+      self.cycles.period()
+  }
 
-              if next_cell.activity_value(life_channel) > 0 {
-                  alive_count += 1;
-              }
-          }
+  /// Generation at which the detected cycle was first entered.
+  pub fn cycle_start(&self) -> Option<u64> {
+      self.cycles.start()
+  }
+
+  /// No live cells were left after the last generation.
+  pub fn is_extinct(&self) -> bool {
+      self.cycles.extinct()
+  }
+
+  /// The live cell count has not changed for `stagnation_window` generations.
+  pub fn is_stagnant(&self) -> bool {
+      self.cycles.stagnant()
+  }
+
+  pub fn set_stagnation_window(&mut self, generations: u32) {
+      self.cycles.set_stagnation_window(generations as u64);
+  }
+
+  pub fn stagnation_window(&self) -> u32 {
+      self.cycles.stagnation_window() as u32
+  }
+
+  /// Keep the last `generations` generations so they can be stepped back
+  /// through.  Each is stored as the cells it changed, so the cost follows
+  /// activity rather than grid size.  `0` disables and frees the history.
+  pub fn set_history_capacity(&mut self, generations: u32) {
+      self.history.set_capacity(generations as usize);
+  }
+
+  pub fn history_capacity(&self) -> u32 {
+      self.history.capacity() as u32
+  }
+
+  /// Oldest generation `seek` can reach.
+  pub fn history_start(&self) -> u64 {
+      self.generation - self.history.back_len() as u64
+  }
+
+  /// Newest recorded generation; later than `generation()` after stepping back.
+  pub fn history_end(&self) -> u64 {
+      self.generation + self.history.forward_len() as u64
+  }
+
+  /// Return to the previous generation as it was just before being ticked,
+  /// including any brush edits made at that point.  Edits made since the
+  /// current generation was reached are discarded.  Returns false once the
+  /// history is exhausted.
+  pub fn step_back(&mut self) -> bool {
+      if self.history.back_len() == 0 {
+          return false;
+      }
+      // Newest first, so a cell edited twice gets its original value back
+      for edit in self.history.take_pending().into_iter().rev() {
+          self.cells[edit.change.idx as usize] = edit.change.old;
+          self.draw_buffer[edit.change.idx as usize] = false;
+      }
+      let Some(frame) = self.history.back() else { return false };
+      Self::apply_changes(&mut self.cells, &frame.tick, true);
+      for edit in frame.edits.iter().filter(|edit| edit.held) {
+          self.draw_buffer[edit.change.idx as usize] = true;
       }
+      self.rng.set_state(frame.rng_before);
+      self.generation -= 1;
+      self.history_restored();
+      true
+  }
 
-      // Update stats
-      let total = total_size as f32;
-      self.stats.avg_hue = sum_hue as f32 / total;
-      self.stats.avg_saturation = sum_sat as f32 / total;
-      self.stats.avg_luminance = sum_lum as f32 / total;
-      self.stats.avg_alpha = sum_life as f32 / total;
-      self.stats.median_hue = median_from_histogram(&histogram_hue, total_size);
-      self.stats.median_saturation = median_from_histogram(&histogram_sat, total_size);
-      self.stats.median_luminance = median_from_histogram(&histogram_lum, total_size);
-      self.stats.median_alpha = median_from_histogram(&histogram_life, total_size);
-      self.stats.alive_count = alive_count;
-      self.stats.dead_count = total_size - alive_count;
-      self.stats.population_ratio = alive_count as f32 / total as f32;
+  /// Replay the next recorded generation after `step_back`.  Returns false
+  /// if there is nothing to replay; `tick` would compute a new one.
+  pub fn step_forward(&mut self) -> bool {
+      let Some(frame) = self.history.forward() else { return false };
+      // The generation's edits first, as they were made before its tick
+      for edit in &frame.edits {
+          self.cells[edit.change.idx as usize] = edit.change.new;
+          self.draw_buffer[edit.change.idx as usize] = false;
+      }
+      Self::apply_changes(&mut self.cells, &frame.tick, false);
+      self.rng.set_state(frame.rng_after);
+      self.generation += 1;
+      self.history_restored();
+      true
+  }
 
-      std::mem::swap(&mut self.cells, &mut self.next);
+  /// Move to `generation`: back through the history, forward through
+  /// recorded generations and then by ticking.  Returns false, without
+  /// moving, if `generation` is older than the history reaches.
+  pub fn seek(&mut self, generation: u64) -> bool {
+      if generation < self.history_start() {
+          return false;
+      }
+      while self.generation > generation {
+          self.step_back();
+      }
+      while self.generation < generation && self.step_forward() {}
+      while self.generation < generation {
+          let before = self.generation;
+          self.tick_n((generation - before).min(u32::MAX as u64) as u32);
+          if self.generation == before {
+              break;
+          }
+      }
+      true
   }
 
   pub fn resize(&mut self, new_width: u32, new_height: u32) {
@@ -644,6 +1051,17 @@ impl Universe {
     self.cells = new_cells;
     self.next = new_next;
     self.draw_buffer = new_draw_buffer;
+    if self.binary.is_some() {
+      self.binary = Some(BitGrid::new(new_width, new_height, self.params.rule));
+    }
+    self.reload_binary();
+    self.tiles = ActiveTiles::new(new_width, new_height);
+    self.trail.clear(new_size);
+    self.history.clear();
+    self.journal.clear();
+    self.cycles.invalidate();
+    self.ages.reset(&self.cells, self.params.life_channel);
+    self.activity.reset(self.cells.len());
   }
 
   pub fn set_cell(&mut self, row: u32, col: u32, hue: u8, sat: u8, lum: u8, t: u8) {
@@ -652,75 +1070,1162 @@ impl Universe {
     }
     if row < self.height && col < self.width {
       let idx = self.index(row, col);
-      self.cells[idx] = Individual { hue, saturation: sat, luminance: lum, alpha: t};
-      self.draw_buffer[idx] = true;
+      let new = Individual { hue, saturation: sat, luminance: lum, alpha: t};
+      self.journal.record(CellChange { idx: idx as u32, old: self.cells[idx], new });
+      self.write_cell(idx, new);
     }
   }
 
-  #[wasm_bindgen]
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
   pub fn cells_ptr(&self) -> *const u8 {
     self.cells.as_ptr() as *const u8
   }
 
-  #[wasm_bindgen]
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
   pub fn cells_len(&self) -> usize {
     self.cells.len() * std::mem::size_of::<Individual>()
   }
 
-  pub fn width(&self) -> u32 { self.width }
-  pub fn height(&self) -> u32 { self.height }
-
-  pub fn toggle(&mut self, row: u32, col: u32) {
-    let idx = self.index(row, col);
-    let cell = &mut self.cells[idx];
-    cell.luminance = if cell.luminance > 0 { 0 } else { 255 };
+  /// Maintain an RGBA framebuffer alongside the cells, refreshed after
+  /// every generation, history step and state load; call `render` to show
+  /// edits made in between.  Off by default; disabling frees it.
+  pub fn set_rgba_enabled(&mut self, enabled: bool) {
+      self.render.enabled = enabled;
+      if enabled {
+          self.render();
+      } else {
+          self.render.rgba = Vec::new();
+      }
   }
 
-  #[wasm_bindgen]
-  pub fn dispose(&mut self) {
-      self.cells.clear();
-      self.next.clear();
-      self.draw_buffer.clear();
-      self.width = 0;
-      self.height = 0;
+  pub fn rgba_enabled(&self) -> bool {
+      self.render.enabled
   }
 
-  /// Save the current state of the universe and return it as a Vec<u8>
-  pub fn save_state(&self) -> Vec<u8> {
-      let mut state = Vec::with_capacity(self.cells_len());
-      let cells_ptr = self.cells.as_ptr() as *const u8;
-      unsafe {
-          state.extend_from_slice(std::slice::from_raw_parts(cells_ptr, self.cells_len()));
+  /// Display modes of the client shader: `use_alpha` keeps cell alpha,
+  /// `use_rgb` shows the raw channels as RGB instead of HSL.
+  pub fn set_display_mode(&mut self, use_alpha: bool, use_rgb: bool) {
+      self.render.use_alpha = use_alpha;
+      self.render.use_rgb = use_rgb;
+      if self.render.enabled {
+          self.render();
       }
-      state
   }
 
-  /// Load a previously saved state into the universe
-  /// Returns true if successful, false if the state size doesn't match
-  pub fn load_state(&mut self, state: &[u8]) -> bool {
-      if state.len() != self.cells_len() {
-          return false;
-      }
+  pub fn use_alpha(&self) -> bool {
+      self.render.use_alpha
+  }
 
-      let cells_ptr = self.cells.as_mut_ptr() as *mut u8;
-      unsafe {
-          std::ptr::copy_nonoverlapping(state.as_ptr(), cells_ptr, state.len());
-      }
+  pub fn use_rgb(&self) -> bool {
+      self.render.use_rgb
+  }
 
-      // Also update the next buffer to match
-      self.next.copy_from_slice(&self.cells);
-      true
+  /// Render through the built-in colour map `name` (see `palette_names`)
+  /// instead of converting from HSL.
+  pub fn set_palette(&mut self, name: &str) -> Result<(), String> {
+      let palette = Palette::named(name).ok_or_else(|| format!("unknown palette '{}'", name))?;
+      self.use_palette(Some(palette));
+      Ok(())
   }
-}
 
-// Test accessors outside wasm_bindgen
-impl Universe {
-  #[cfg(test)]
-  pub fn cells(&self) -> &Vec<Individual> { &self.cells }
+  /// Render through a user palette given as JSON stops.
+  pub fn set_palette_json(&mut self, json: &str) -> Result<(), String> {
+      self.use_palette(Some(Palette::from_json(json)?));
+      Ok(())
+  }
+
+  /// Render through a full 256-entry gradient given as 1024 RGBA bytes.
+  pub fn set_palette_rgba(&mut self, rgba: &[u8]) -> Result<(), String> {
+      self.use_palette(Some(Palette::from_rgba("custom", rgba)?));
+      Ok(())
+  }
+
+  /// Go back to the HSL conversion.
+  pub fn clear_palette(&mut self) {
+      self.use_palette(None);
+  }
+
+  /// Name of the current palette, or `None` when rendering HSL.
+  pub fn palette_name(&self) -> Option<String> {
+      self.render.palette.as_ref().map(|palette| palette.name.clone())
+  }
+
+  /// The current palette as 1024 RGBA bytes, empty when rendering HSL.
+  pub fn palette_rgba(&self) -> Vec<u8> {
+      self.render.palette.as_ref().map_or_else(Vec::new, Palette::to_rgba)
+  }
+
+  /// The channel whose value indexes the palette; alpha by default.
+  pub fn set_palette_channel(&mut self, channel: LifeChannel) {
+      self.render.palette_channel = channel;
+      if self.render.enabled && self.render.palette.is_some() {
+          self.render();
+      }
+  }
+
+  pub fn palette_channel(&self) -> LifeChannel {
+      self.render.palette_channel
+  }
+
+  /// Names of the built-in palettes.
+  pub fn palette_names() -> Vec<String> {
+      PALETTE_NAMES.iter().map(|name| name.to_string()).collect()
+  }
+
+  /// What the framebuffer shows: the cells, or a heat map of their ages or
+  /// activity.
+  pub fn set_render_source(&mut self, source: RenderSource) {
+      self.render.source = source;
+      if self.render.enabled {
+          self.render();
+      }
+  }
+
+  pub fn render_source(&self) -> RenderSource {
+      self.render.source
+  }
+
+  /// Age, in generations, at the hot end of the age heat map.
+  pub fn set_age_range(&mut self, generations: u32) {
+      self.render.age_range = generations.max(1);
+      if self.render.enabled && self.render.source == RenderSource::Age {
+          self.render();
+      }
+  }
+
+  pub fn age_range(&self) -> u32 {
+      self.render.age_range
+  }
+
+  /// Count, per cell, the generations since it was born or, for dead
+  /// cells and ghosts, since it died.  Any change of state restarts the
+  /// count, including edits; loads, resizes and history steps restart
+  /// every cell.  Off by default; disabling frees the buffer.
+  pub fn set_age_tracking(&mut self, enabled: bool) {
+      self.ages.enabled = enabled;
+      self.ages.reset(&self.cells, self.params.life_channel);
+      if !enabled {
+          self.stats.mean_age = 0.0;
+          self.stats.max_age = 0;
+      }
+  }
+
+  pub fn age_tracking(&self) -> bool {
+      self.ages.enabled
+  }
+
+  /// The ages as `u32`s, one per cell; `ages_len` counts entries, not
+  /// bytes.
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn ages_ptr(&self) -> *const u32 {
+    self.ages.ages.as_ptr()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn ages_len(&self) -> usize {
+    self.ages.ages.len()
+  }
+
+  /// Keep a moving average, per cell, of how often it is born or dies: 0
+  /// for settled ash, approaching 1 for cells flipping every generation.
+  /// Edits between generations don't count.  Off by default; disabling
+  /// frees the buffer.
+  pub fn set_activity_tracking(&mut self, enabled: bool) {
+      self.activity.enabled = enabled;
+      self.activity.reset(self.cells.len());
+  }
+
+  pub fn activity_tracking(&self) -> bool {
+      self.activity.enabled
+  }
+
+  /// Generations the activity average roughly spans (default 32).
+  pub fn set_activity_window(&mut self, generations: u32) {
+      self.activity.settle();
+      self.activity.window = generations.max(1);
+  }
+
+  pub fn activity_window(&self) -> u32 {
+      self.activity.window
+  }
+
+  /// Mean activity of the `w` x `h` region at `(x, y)`, clipped to the
+  /// grid, e.g. to find busy or settled areas to stamp into.
+  pub fn region_activity(&self, x: u32, y: u32, w: u32, h: u32) -> f32 {
+      let x1 = x.saturating_add(w).min(self.width);
+      let y1 = y.saturating_add(h).min(self.height);
+      self.activity.region_mean(self.width, (x, y, x1, y1))
+  }
+
+  /// The activity as `f32`s, one per cell; `activity_len` counts entries,
+  /// not bytes.
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn activity_ptr(&mut self) -> *const f32 {
+    self.activity.settle();
+    self.activity.values.as_ptr()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn activity_len(&self) -> usize {
+    self.activity.values.len()
+  }
+
+  /// Accumulate every generation into a long-exposure trail, blended by
+  /// `set_trail_mode` and rendered like the framebuffer (display mode,
+  /// palette and source).  The trail never affects the cells.  Off by
+  /// default; enabling starts from black and disabling frees it.
+  pub fn set_trail_enabled(&mut self, enabled: bool) {
+      self.trail.enabled = enabled;
+      self.trail.clear(self.cells.len());
+  }
+
+  pub fn trail_enabled(&self) -> bool {
+      self.trail.enabled
+  }
+
+  /// How generations blend into the trail and how much of the previous
+  /// trail survives each one (`0.0..=1.0`; default max with 1, keeping
+  /// everything).
+  pub fn set_trail_mode(&mut self, mode: TrailMode, persistence: f32) {
+      self.trail.mode = mode;
+      self.trail.persistence = persistence.clamp(0.0, 1.0);
+  }
+
+  pub fn trail_mode(&self) -> TrailMode {
+      self.trail.mode
+  }
+
+  pub fn trail_persistence(&self) -> f32 {
+      self.trail.persistence
+  }
+
+  /// Brightness of `trail_rgba`; below 1 tames additive trails.
+  pub fn set_trail_exposure(&mut self, exposure: f32) {
+      self.trail.exposure = exposure.max(0.0);
+  }
+
+  pub fn trail_exposure(&self) -> f32 {
+      self.trail.exposure
+  }
+
+  /// Fade the trail back to black.
+  pub fn clear_trail(&mut self) {
+      self.trail.clear(self.cells.len());
+  }
+
+  /// The trail as straight-alpha RGBA for stills: the coverage-weighted
+  /// colour of what passed through each cell, with coverage scaled by the
+  /// exposure as alpha.  Empty while disabled.
+  pub fn trail_rgba(&self) -> Vec<u8> {
+      self.trail.to_rgba()
+  }
+
+  /// The raw accumulation, alpha-premultiplied RGBA `f32`s; `trail_len`
+  /// counts floats, not bytes.
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn trail_ptr(&self) -> *const f32 {
+    self.trail.values.as_ptr()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn trail_len(&self) -> usize {
+    self.trail.values.len()
+  }
+
+  /// The grid as the framebuffer shows it (display mode, palette and
+  /// render source) encoded as a PNG, whether or not the framebuffer is
+  /// maintained.
+  pub fn export_png(&self) -> Result<Vec<u8>, String> {
+      let activity = self.activity.current();
+      let layers = Layers { cells: &self.cells, ages: &self.ages.ages, activity: &activity };
+      let pixels = (0..self.cells.len()).flat_map(|idx| self.render.pixel(&layers, idx)).collect();
+      RgbaImage::new(self.width, self.height, pixels).encode_png()
+  }
+
+  /// The cells losslessly as a PNG whose red, green, blue and alpha hold
+  /// hue, saturation, luminance and alpha.  `import_png` recognises it.
+  pub fn export_png_raw(&self) -> Result<Vec<u8>, String> {
+      let pixels = self.cells.iter().flat_map(|c| [c.hue, c.saturation, c.luminance, c.alpha]).collect();
+      RgbaImage { raw_hsla: true, ..RgbaImage::new(self.width, self.height, pixels) }.encode_png()
+  }
+
+  /// Replace every cell from a PNG, scaled to the grid by nearest pixel.
+  /// Raw exports restore the cells exactly; other images are converted
+  /// from RGB to HSL, keeping their alpha.
+  pub fn import_png(&mut self, png: &[u8]) -> Result<(), String> {
+      if self.cells.is_empty() {
+          return Err("cannot import into an empty universe".to_string());
+      }
+      let image = RgbaImage::decode_png(png)?.resize_nearest(self.width, self.height);
+      for (cell, p) in self.cells.iter_mut().zip(image.pixels.chunks_exact(4)) {
+          *cell = if image.raw_hsla {
+              Individual { hue: p[0], saturation: p[1], luminance: p[2], alpha: p[3] }
+          } else {
+              Individual::from_rgba(p[0], p[1], p[2], p[3])
+          };
+      }
+      self.cells_replaced();
+      Ok(())
+  }
+
+  /// Run a copy of the universe forward and encode it as an animated GIF
+  /// or APNG, rendered like the framebuffer.  This universe is left as it
+  /// was.
+  pub fn export_animation(&self, options: &AnimationOptions) -> Result<Vec<u8>, String> {
+      let (x, y, w, h) = options.region(self.width, self.height)?;
+      let (out_w, out_h) = options.output_size(w, h)?;
+      let mut run = self.fork();
+      let (mut done, mut first) = (0, true);
+      animation::encode(options, out_w, out_h, options.frame_count(), || {
+          if !std::mem::take(&mut first) {
+              let n = options.every.max(1).min(options.generations - done);
+              run.tick_n(n);
+              done += n;
+          }
+          run.activity.settle();
+          let layers = Layers { cells: &run.cells, ages: &run.ages.ages, activity: &run.activity.values };
+          let mut pixels = Vec::with_capacity(w as usize * h as usize * 4);
+          for row in y..y + h {
+              for col in x..x + w {
+                  pixels.extend_from_slice(&run.render.pixel(&layers, run.index(row, col)));
+              }
+          }
+          RgbaImage::new(w, h, pixels).resize_nearest(out_w, out_h).pixels
+      })
+  }
+
+  /// Stamp a PNG onto the grid, resampled and placed per `options`, as one
+  /// undoable edit.  Transparent pixels leave their cells alone.  With
+  /// dithering, each pixel's brightness decides whether its cell is born
+  /// (life channel 255) or dies (0); otherwise cells take the pixel's
+  /// colour and alpha.
+  pub fn import_image(&mut self, png: &[u8], options: &ImportOptions) -> Result<(), String> {
+      let image = RgbaImage::decode_png(png)?;
+      self.stamp_image(&image, options);
+      Ok(())
+  }
+
+  /// `import_image` for raw RGBA, such as a canvas's `ImageData`.
+  pub fn import_rgba(&mut self, width: u32, height: u32, rgba: &[u8], options: &ImportOptions) -> Result<(), String> {
+      if rgba.len() != width as usize * height as usize * 4 {
+          return Err(format!("{}x{} RGBA needs {} bytes, got {}", width, height, width as usize * height as usize * 4, rgba.len()));
+      }
+      self.stamp_image(&RgbaImage::new(width, height, rgba.to_vec()), options);
+      Ok(())
+  }
+
+  /// Redraw the whole framebuffer, e.g. after edits made since the last
+  /// generation.
+  pub fn render(&mut self) {
+      if self.render.source == RenderSource::Activity {
+          self.activity.settle();
+      }
+      self.render.render_all(&Layers { cells: &self.cells, ages: &self.ages.ages, activity: &self.activity.values });
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn rgba_ptr(&self) -> *const u8 {
+    self.render.rgba.as_ptr()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn rgba_len(&self) -> usize {
+    self.render.rgba.len()
+  }
+
+  pub fn width(&self) -> u32 { self.width }
+  pub fn height(&self) -> u32 { self.height }
+
+  /// Flip a cell's luminance between 0 and 255.  Unlike a drawn cell it
+  /// evolves in the very next generation.
+  pub fn toggle(&mut self, row: u32, col: u32) {
+    let idx = self.index(row, col);
+    let mut cell = self.cells[idx];
+    cell.luminance = if cell.luminance > 0 { 0 } else { 255 };
+    self.record_cell(idx, cell, false);
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen)]
+  pub fn dispose(&mut self) {
+      self.cells.clear();
+      self.next.clear();
+      self.draw_buffer.clear();
+      self.binary = None;
+      self.tiles = ActiveTiles::new(0, 0);
+      self.history.clear();
+      self.journal.clear();
+      self.ages.reset(&[], self.params.life_channel);
+      self.activity.reset(0);
+      self.trail.clear(0);
+      self.width = 0;
+      self.height = 0;
+  }
+
+  /// Save the universe as a self-describing container: dimensions, params,
+  /// generation, seed and RNG state, cells, pending brush edits, cell ages
+  /// and activity if tracked and a checksum.
+  pub fn save_state(&self) -> Vec<u8> {
+      let mut layers = Vec::new();
+      if self.draw_buffer.iter().any(|&drawn| drawn) {
+          let mut bits = vec![0u8; self.draw_buffer.len().div_ceil(8)];
+          for (idx, _) in self.draw_buffer.iter().enumerate().filter(|(_, &drawn)| drawn) {
+              bits[idx / 8] |= 1 << (idx % 8);
+          }
+          layers.push(Layer { tag: LAYER_DRAWN, data: bits });
+      }
+      if self.ages.enabled {
+          layers.push(Layer { tag: LAYER_AGES, data: self.ages.to_bytes() });
+      }
+      if self.activity.enabled {
+          layers.push(Layer { tag: LAYER_ACTIVITY, data: self.activity.to_bytes() });
+      }
+      savefile::encode(&SaveData {
+          width: self.width,
+          height: self.height,
+          params: self.params,
+          generation: self.generation,
+          seed: self.seed,
+          rng_state: self.rng.state(),
+          cells: self.cells.clone(),
+          layers,
+      })
+  }
+
+  /// `save_state` compressed with per-channel delta coding and an LZ pass.
+  /// Mostly empty or flat universes shrink to a tiny fraction of their size.
+  pub fn save_state_compressed(&self) -> Vec<u8> {
+      compress::compress(&self.save_state())
+  }
+
+  /// Load a state written by `save_state_compressed`.
+  pub fn load_state_compressed(&mut self, state: &[u8]) -> Result<(), String> {
+      let container = compress::decompress(state)?;
+      self.load_state(&container)
+  }
+
+  /// Load a state written by `save_state`, resizing the universe to match
+  /// it.  Legacy raw saves (the cells, optionally followed by the RNG state
+  /// and seed) are still accepted if they fit the current dimensions.
+  pub fn load_state(&mut self, state: &[u8]) -> Result<(), String> {
+      if savefile::is_container(state) {
+          let save = savefile::decode(state)?;
+          self.restore(save);
+          return Ok(());
+      }
+
+      let cells_len = self.cells_len();
+      if state.len() != cells_len && state.len() != cells_len + RNG_STATE_LEN {
+          return Err(format!(
+              "state of {} bytes is neither a saved universe nor a raw {}x{} grid ({} or {} bytes)",
+              state.len(),
+              self.width,
+              self.height,
+              cells_len,
+              cells_len + RNG_STATE_LEN,
+          ));
+      }
+
+      for (cell, c) in self.cells.iter_mut().zip(state.chunks_exact(4)) {
+          *cell = Individual { hue: c[0], saturation: c[1], luminance: c[2], alpha: c[3] };
+      }
+      if let Some(trailer) = state.get(cells_len..) {
+          if trailer.len() == RNG_STATE_LEN {
+              let word = |i: usize| u64::from_le_bytes(trailer[i..i + 8].try_into().unwrap());
+              self.rng.set_state(word(0));
+              self.seed = word(8);
+          }
+      }
+
+      self.draw_buffer.fill(false);
+      self.next.copy_from_slice(&self.cells);
+      self.reload_binary();
+      self.tiles.mark_all();
+      self.cycles.invalidate();
+      self.history.clear();
+      self.journal.clear();
+      self.ages.reset(&self.cells, self.params.life_channel);
+      self.activity.reset(self.cells.len());
+      Ok(())
+  }
+}
+
+/// Per-generation constants derived from `LifeParams` for `tick`.
+struct TickRules {
+    rule: u32,
+    sat_decay_term: f32,
+    lum_decay_term: f32,
+    life_decay_term: f32,
+    sat_recovery: f32,
+    lum_decay_factor: f32,
+    life_decay_factor: f32,
+    sat_ghost_factor: f32,
+    hue_drift_strength: f32,
+    hue_lerp_factor: f32,
+    life_channel: LifeChannel,
+}
+
+impl TickRules {
+    fn new(params: &LifeParams) -> Self {
+        use std::f32::consts::E;
+
+        let decay_step_f = params.decay_step as f32;
+        Self {
+            rule: params.rule,
+            sat_decay_term: decay_step_f * E * params.sat_decay_factor,
+            lum_decay_term: decay_step_f * E * params.lum_decay_factor,
+            life_decay_term: decay_step_f * E * params.life_decay_factor,
+            sat_recovery: params.recovery_step as f32 * params.sat_recovery_factor,
+            lum_decay_factor: params.lum_decay_factor,
+            life_decay_factor: params.life_decay_factor,
+            sat_ghost_factor: params.sat_ghost_factor,
+            hue_drift_strength: params.hue_drift_strength,
+            hue_lerp_factor: params.hue_lerp_factor,
+            life_channel: params.life_channel,
+        }
+    }
+}
+
+impl Universe {
+  /// One generation, gathering statistics up to `level`.
+  fn step(&mut self, level: StatsLevel) {
+      if self.width == 0 || self.height == 0 {
+          return;
+      }
+
+      let rng_before = self.rng.state();
+      if self.binary.is_some() {
+          self.tick_binary();
+          self.generation += 1;
+          // Every layer below reads the cells, so without them the sync
+          // waits for `tick` or `tick_n` to return
+          let layers = self.history.enabled() || self.cycles.enabled || self.ages.enabled
+              || self.activity.enabled || self.trail.enabled || self.render.enabled;
+          if level == StatsLevel::None && !layers {
+              return;
+          }
+          let delta = self.sync_binary();
+          if level > StatsLevel::None {
+              self.refresh_stats(level);
+          }
+          if self.history.enabled() {
+              self.history.commit(delta, rng_before, self.rng.state());
+          }
+          self.observe_cycles();
+          self.update_ages(level);
+          self.update_activity();
+          self.accumulate_trail();
+          self.refresh_rgba();
+          return;
+      }
+
+      let rules = TickRules::new(&self.params);
+      let life_channel = self.params.life_channel;
+
+      // Pre-allocate neighbors array to avoid repeated allocations
+      let mut neighbors = [0usize; 8];
+      let record = self.history.enabled();
+      let mut delta = Vec::new();
+
+      // Only tiles that changed last generation, or border a tile that did, can
+      // change now; `next` already holds the current values everywhere else.
+      let active = self.tiles.active_set();
+      self.tiles.reset_changed();
+      for (tile, _) in active.iter().enumerate().filter(|(_, &a)| a) {
+          let (x0, y0, x1, y1) = self.tiles.bounds(tile, self.width, self.height);
+          let mut tile_stats = TileStats::new(level);
+          let mut changed = false;
+
+          for row in y0..y1 {
+              for col in x0..x1 {
+                  let idx = self.index(row, col);
+                  let cell = self.cells[idx];
+
+                  let next_cell = if std::mem::take(&mut self.draw_buffer[idx]) {
+                      // Drawn cells are held for a generation, then evolve
+                      changed = true;
+                      cell
+                  } else {
+                      self.get_neighbour_indices(row, col, &mut neighbors);
+                      Self::evolve(&self.cells, cell, &neighbors, &rules, &mut self.rng)
+                  };
+
+                  if next_cell != cell {
+                      changed = true;
+                      if record {
+                          delta.push(CellChange { idx: idx as u32, old: cell, new: next_cell });
+                      }
+                  }
+                  self.next[idx] = next_cell;
+                  if level > StatsLevel::None {
+                      tile_stats.add(next_cell, life_channel);
+                  }
+              }
+          }
+
+          if level > StatsLevel::None {
+              self.tiles.update(tile, tile_stats, level, changed);
+          } else {
+              self.tiles.invalidate(tile, changed);
+          }
+      }
+
+      self.generation += 1;
+      std::mem::swap(&mut self.cells, &mut self.next);
+      if level > StatsLevel::None {
+          self.refresh_stats(level);
+      }
+      if record {
+          self.history.commit(delta, rng_before, self.rng.state());
+      }
+      self.observe_cycles();
+      self.update_ages(level);
+      self.update_activity();
+      self.accumulate_trail();
+      self.refresh_rgba();
+  }
+
+  fn use_palette(&mut self, palette: Option<Palette>) {
+      self.render.palette = palette;
+      if self.render.enabled {
+          self.render();
+      }
+  }
+
+  /// Redraw the tiles that changed this generation.
+  fn refresh_rgba(&mut self) {
+      if !self.render.enabled {
+          return;
+      }
+      if self.render.source != RenderSource::Cells {
+          // Every cell ages, changed or not
+          self.render();
+          return;
+      }
+      let layers = Layers { cells: &self.cells, ages: &self.ages.ages, activity: &self.activity.values };
+      for tile in 0..self.tiles.len() {
+          if self.tiles.is_changed(tile) {
+              let bounds = self.tiles.bounds(tile, self.width, self.height);
+              self.render.render_region(&layers, self.width, bounds);
+          }
+      }
+  }
+
+  fn update_ages(&mut self, level: StatsLevel) {
+      if !self.ages.enabled {
+          return;
+      }
+      self.ages.update(&self.cells, self.params.life_channel);
+      if level > StatsLevel::None {
+          self.stats.mean_age = self.ages.mean;
+          self.stats.max_age = self.ages.max;
+      }
+  }
+
+  /// Blend this generation, as the framebuffer would show it, into the
+  /// trail.
+  fn accumulate_trail(&mut self) {
+      if !self.trail.enabled {
+          return;
+      }
+      if self.render.source == RenderSource::Activity {
+          self.activity.settle();
+      }
+      let layers = Layers { cells: &self.cells, ages: &self.ages.ages, activity: &self.activity.values };
+      let render = &self.render;
+      self.trail.accumulate((0..self.cells.len()).map(|idx| render.pixel(&layers, idx)));
+  }
+
+  /// Fold this generation into the activity, visiting only the tiles it
+  /// changed.
+  fn update_activity(&mut self) {
+      let (tiles, width, height) = (&self.tiles, self.width, self.height);
+      let changed = (0..tiles.len()).filter(|&tile| tiles.is_changed(tile));
+      let regions = changed.map(|tile| tiles.bounds(tile, width, height));
+      self.activity.update(&self.cells, &self.next, width, regions, self.params.life_channel);
+  }
+
+  fn observe_cycles(&mut self) {
+      if !self.cycles.enabled {
+          return;
+      }
+      let (channel, full, count) = (self.params.life_channel, self.cycles.full, self.tiles.len());
+      // Every edit voids the table, so between rehashes only ticks change
+      // cells, and they flag the tiles they change
+      let all = self.cycles.needs_rehash(count);
+      for tile in (0..count).filter(|&tile| all || self.tiles.is_changed(tile)) {
+          let bounds = self.tiles.bounds(tile, self.width, self.height);
+          let (hash, alive) = cycles::hash_region(&self.cells, self.width, bounds, channel, full);
+          self.cycles.set_tile(tile, hash, alive, count);
+      }
+      self.cycles.observe(self.generation);
+  }
+
+  /// Write `changes` back to the grid, newest first, restoring their old
+  /// values (`undo`) or oldest first applying the new ones.
+  fn apply_changes(cells: &mut [Individual], changes: &[CellChange], undo: bool) {
+      if undo {
+          for change in changes.iter().rev() {
+              cells[change.idx as usize] = change.old;
+          }
+      } else {
+          for change in changes {
+              cells[change.idx as usize] = change.new;
+          }
+      }
+  }
+
+  /// Draw `cell` at `idx`: hold it for a generation and record it as an
+  /// edit in the history.
+  fn write_cell(&mut self, idx: usize, cell: Individual) {
+      self.record_cell(idx, cell, true);
+  }
+
+  /// Write `cell` at `idx` as an edit in the history, held for a
+  /// generation if `held`.
+  fn record_cell(&mut self, idx: usize, cell: Individual, held: bool) {
+      self.history.record_edit(CellChange { idx: idx as u32, old: self.cells[idx], new: cell }, held);
+      self.cells[idx] = cell;
+      if held {
+          self.draw_buffer[idx] = true;
+      }
+      let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+      if let Some(grid) = self.binary.as_mut() {
+          grid.set(row, col, cell.activity_value(self.params.life_channel) > 0);
+          if held {
+              grid.hold(row, col);
+          }
+          // No full pass will refresh `next` or the tile's statistics
+          self.next[idx] = cell;
+          self.tiles.invalidate_cell(row, col);
+      }
+      self.tiles.mark(row, col);
+      self.cycles.invalidate();
+  }
+
+  /// A copy that evolves exactly like this universe, without its history,
+  /// undo journal, cycle detection or trail, for exporting runs.
+  fn fork(&self) -> Universe {
+      Universe {
+          cells: self.cells.clone(),
+          next: self.next.clone(),
+          draw_buffer: self.draw_buffer.clone(),
+          stats: self.stats.clone(),
+          binary: self.binary.clone(),
+          tiles: self.tiles.clone(),
+          rng: self.rng.clone(),
+          stats_mode: StatsMode::Off,
+          render: self.render.detached(),
+          ages: self.ages.clone(),
+          activity: self.activity.clone(),
+          params: self.params,
+          pattern_color: self.pattern_color,
+          ..Universe::new(self.width, self.height)
+      }
+  }
+
+  /// Rewrite every cell in columns `x..x_end` of rows `y..y_end` with
+  /// `update(row, col, cell)`, then resync everything as a load does.
+  pub(crate) fn replace_region(
+      &mut self,
+      (x, y, x_end, y_end): (u32, u32, u32, u32),
+      mut update: impl FnMut(u32, u32, Individual) -> Individual,
+  ) {
+      for row in y..y_end.min(self.height) {
+          for col in x..x_end.min(self.width) {
+              let idx = self.index(row, col);
+              self.cells[idx] = update(row, col, self.cells[idx]);
+          }
+      }
+      self.cells_replaced();
+  }
+
+  /// Every cell was overwritten: drop pending draws, history, undo journal
+  /// and derived layers as a load does.
+  fn cells_replaced(&mut self) {
+      self.draw_buffer.fill(false);
+      self.next.copy_from_slice(&self.cells);
+      self.reload_binary();
+      self.tiles.mark_all();
+      self.cycles.invalidate();
+      self.history.clear();
+      self.journal.clear();
+      self.ages.reset(&self.cells, self.params.life_channel);
+      self.activity.reset(self.cells.len());
+      if self.render.enabled {
+          self.render();
+      }
+  }
+
+  /// Cells were rewritten by the history: resync `next` and drop cached tile
+  /// statistics so the next tick starts from a clean slate.
+  fn history_restored(&mut self) {
+      self.next.copy_from_slice(&self.cells);
+      self.reload_binary();
+      self.tiles.invalidate_all();
+      self.cycles.invalidate();
+      self.ages.reset(&self.cells, self.params.life_channel);
+      self.activity.reset(self.cells.len());
+      if self.render.enabled {
+          self.render();
+      }
+  }
+
+  /// Bring every tile's cached statistics in `next` up to `level` and
+  /// publish the totals.
+  fn refresh_stats(&mut self, level: StatsLevel) {
+      let life_channel = self.params.life_channel;
+      let total_size = (self.width * self.height) as usize;
+
+      for tile in self.tiles.stale(level) {
+          let (x0, y0, x1, y1) = self.tiles.bounds(tile, self.width, self.height);
+          let mut tile_stats = TileStats::new(level);
+          for row in y0..y1 {
+              for col in x0..x1 {
+                  tile_stats.add(self.cells[self.index(row, col)], life_channel);
+              }
+          }
+          self.tiles.update(tile, tile_stats, level, false);
+      }
+
+      let total = total_size as f32;
+      let alive_count = self.tiles.alive();
+      self.stats.avg_hue = self.tiles.sum(0) as f32 / total;
+      self.stats.avg_saturation = self.tiles.sum(1) as f32 / total;
+      self.stats.avg_luminance = self.tiles.sum(2) as f32 / total;
+      self.stats.avg_alpha = self.tiles.sum(3) as f32 / total;
+      self.stats.alive_count = alive_count;
+      self.stats.dead_count = total_size - alive_count;
+      self.stats.population_ratio = alive_count as f32 / total;
+      if level < StatsLevel::Full {
+          return;
+      }
+      self.stats.median_hue = median_from_histogram(self.tiles.histogram(0), total_size);
+      self.stats.median_saturation = median_from_histogram(self.tiles.histogram(1), total_size);
+      self.stats.median_luminance = median_from_histogram(self.tiles.histogram(2), total_size);
+      self.stats.median_alpha = median_from_histogram(self.tiles.histogram(3), total_size);
+  }
+
+  /// The next state of `cell` given the indices of its eight neighbours.
+  fn evolve(
+      cells: &[Individual],
+      cell: Individual,
+      neighbors: &[usize; 8],
+      rules: &TickRules,
+      rng: &mut SplitMix64,
+  ) -> Individual {
+      use std::f32::consts::TAU;
+
+      let tau_div_255 = TAU / 255.0;
+      let life_channel = rules.life_channel;
+
+      let mut live_neighbors = 0u8;
+      let mut sat_sum = 0.0f32;
+      let mut lum_sum = 0.0f32;
+      let mut alpha_sum = 0.0f32;
+      let mut strongest_hue = 0u8;
+      let mut max_lum = 0u8;
+      let mut sin_sum = 0.0f32;
+      let mut cos_sum = 0.0f32;
+
+      // Single pass through neighbors for all calculations
+      for &nidx in neighbors {
+          let n = cells[nidx];
+          if n.activity_value(life_channel) > 0 {
+              live_neighbors += 1;
+              sat_sum += n.saturation as f32;
+              lum_sum += n.luminance as f32;
+              alpha_sum += n.alpha as f32;
+
+              if n.luminance > max_lum {
+                  max_lum = n.luminance;
+                  strongest_hue = n.hue;
+              }
+              let angle = (n.hue as f32) * tau_div_255;
+              sin_sum += angle.sin();
+              cos_sum += angle.cos();
+          }
+      }
+
+      let bit_index = live_neighbors as u32
+          + if cell.activity_value(life_channel) > 0 { 9 } else { 0 };
+      let next_alive = ((rules.rule >> bit_index) & 1) == 1;
+
+      match (cell.activity_value(life_channel) > 0, next_alive) {
+          (true, true) => {
+              let new_sat = (cell.saturation as f32 + rules.sat_recovery).min(255.0);
+              Individual { saturation: new_sat as u8, ..cell }
+          }
+          (true, false) => {
+              Individual {
+                  hue: cell.hue,
+                  saturation: (cell.saturation as f32 - rules.sat_decay_term).max(0.0) as u8,
+                  luminance: (cell.luminance as f32 - rules.lum_decay_term).max(0.0) as u8,
+                  alpha: (cell.alpha as f32 - rules.life_decay_term).max(0.0) as u8,
+              }
+          }
+          (false, true) => {
+              let avg = |sum: f32| {
+                  if live_neighbors == 0 {
+                      0
+                  } else {
+                      (sum / live_neighbors as f32).round() as u8
+                  }
+              };
+
+              let mean_angle = if sin_sum == 0.0 && cos_sum == 0.0 {
+                  0.0
+              } else {
+                  sin_sum.atan2(cos_sum)
+              };
+
+              let strongest_angle = (strongest_hue as f32) * tau_div_255;
+              let mixed_angle = (1.0 - rules.hue_lerp_factor) * mean_angle
+                  + rules.hue_lerp_factor * strongest_angle;
+              let drift = (rng.random::<f32>() - 0.5) * rules.hue_drift_strength * 2.0;
+              let final_angle = (mixed_angle + drift).rem_euclid(TAU);
+              let hue = ((final_angle / TAU) * 255.0).round() as u8;
+
+              let v = |ch, fallback| if life_channel == ch { 255 } else { fallback };
+
+              Individual {
+                  hue:        v(LifeChannel::Hue,        hue),
+                  saturation: v(LifeChannel::Saturation, avg(sat_sum).saturating_add(1)),
+                  luminance:  v(LifeChannel::Luminance,  avg(lum_sum).saturating_add(1)),
+                  alpha:      v(LifeChannel::Alpha,      avg(alpha_sum).saturating_add(1)),
+              }
+          }
+          _ => Individual {
+              hue: cell.hue,
+              saturation: (cell.saturation as f32 * rules.sat_ghost_factor) as u8,
+              luminance: (cell.luminance as f32 * rules.lum_decay_factor) as u8,
+              alpha: (cell.alpha as f32 * rules.life_decay_factor) as u8,
+          },
+      }
+  }
+
+  /// One generation of the bit-packed engine used by `set_binary_mode`.
+  /// The grid stays resident between generations and `cells` only catch up
+  /// with it in `sync_binary`.
+  fn tick_binary(&mut self) {
+      let Some(grid) = self.binary.as_mut() else { return };
+      // Only tiles that hold a drawn cell or flip one are flagged, as in the
+      // HSLA path
+      self.tiles.reset_changed();
+      for idx in grid.held_cells() {
+          // Drawn cells are held for a generation, then evolve
+          self.draw_buffer[idx] = false;
+          self.tiles.mark(idx as u32 / self.width, idx as u32 % self.width);
+      }
+      grid.set_rule(self.params.rule);
+      grid.step();
+  }
+
+  /// Bring `cells` up to date with the resident bit grid, visiting only the
+  /// cells that flipped since the last sync: those now dead are cleared and
+  /// those now alive take the born colour, as if synced every generation.
+  /// `next` keeps the flipped cells' previous values.  Returns the changes
+  /// if the history is recording.
+  fn sync_binary(&mut self) -> Vec<CellChange> {
+      let Some(grid) = self.binary.as_mut() else { return Vec::new() };
+      for idx in self.flipped.drain(..) {
+          self.next[idx] = self.cells[idx];
+      }
+      let born = binary_born_cell(self.params.life_channel);
+      let record = self.history.enabled();
+      let mut delta = Vec::new();
+      for (idx, alive) in grid.take_changes() {
+          let old = self.cells[idx];
+          let new = if alive { born } else { Individual::default() };
+          if record && new != old {
+              delta.push(CellChange { idx: idx as u32, old, new });
+          }
+          self.next[idx] = old;
+          self.cells[idx] = new;
+          self.flipped.push(idx);
+          self.tiles.invalidate_cell(idx as u32 / self.width, idx as u32 % self.width);
+      }
+      delta
+  }
+
+  /// Reload the binary grid after `cells` were rewritten wholesale, holding
+  /// the drawn cells, and drop every cached tile statistic.
+  fn reload_binary(&mut self) {
+      self.flipped.clear();
+      let Some(grid) = self.binary.as_mut() else { return };
+      grid.load_cells(&self.cells, self.params.life_channel);
+      self.next.copy_from_slice(&self.cells);
+      self.tiles.invalidate_all();
+      for (idx, _) in self.draw_buffer.iter().enumerate().filter(|(_, &drawn)| drawn) {
+          grid.hold(idx as u32 / self.width, idx as u32 % self.width);
+      }
+  }
+}
+
+// Native accessors outside wasm_bindgen
+impl Universe {
+  pub fn cells(&self) -> &Vec<Individual> { &self.cells }
 
-  #[cfg(test)]
   pub fn params(&self) -> &LifeParams { &self.params }
 
+  /// The RGBA framebuffer; empty unless `set_rgba_enabled(true)`.
+  pub fn rgba(&self) -> &[u8] { &self.render.rgba }
+
+  /// Cell ages; empty unless `set_age_tracking(true)`.
+  pub fn ages(&self) -> &[u32] { &self.ages.ages }
+
+  /// Cell activity; empty unless `set_activity_tracking(true)`.
+  pub fn activity(&mut self) -> &[f32] {
+      self.activity.settle();
+      &self.activity.values
+  }
+
+  /// Replace the whole universe with a decoded save.
+  fn restore(&mut self, save: SaveData) {
+      if (save.width, save.height) != (self.width, self.height) {
+          let size = save.cells.len();
+          self.width = save.width;
+          self.height = save.height;
+          self.next = vec![Individual::default(); size];
+          self.draw_buffer = vec![false; size];
+          if self.binary.is_some() {
+              self.binary = Some(BitGrid::new(save.width, save.height, save.params.rule));
+          }
+          self.trail.clear(size);
+      }
+      self.tiles = ActiveTiles::new(save.width, save.height);
+      self.params = save.params;
+      self.generation = save.generation;
+      self.seed = save.seed;
+      self.rng.set_state(save.rng_state);
+
+      self.draw_buffer.fill(false);
+      if let Some(bits) = save.layer(LAYER_DRAWN) {
+          for (idx, drawn) in self.draw_buffer.iter_mut().enumerate() {
+              *drawn = bits.get(idx / 8).is_some_and(|b| b >> (idx % 8) & 1 == 1);
+          }
+      }
+      let ages = save.layer(LAYER_AGES).map(<[u8]>::to_vec);
+      let activity = save.layer(LAYER_ACTIVITY).map(<[u8]>::to_vec);
+      self.cells = save.cells;
+      self.next.copy_from_slice(&self.cells);
+      self.reload_binary();
+      if let Some(ages) = ages {
+          self.ages.enabled = true;
+          self.ages.load_bytes(&ages, &self.cells, self.params.life_channel);
+      } else {
+          self.ages.reset(&self.cells, self.params.life_channel);
+      }
+      self.activity.enabled |= activity.is_some();
+      self.activity.reset(self.cells.len());
+      if let Some(bytes) = activity.filter(|bytes| bytes.len() == self.cells.len() * 4) {
+          for (value, chunk) in self.activity.values.iter_mut().zip(bytes.chunks_exact(4)) {
+              *value = f32::from_le_bytes(chunk.try_into().unwrap());
+          }
+      }
+      self.cycles.invalidate();
+      self.history.clear();
+      self.journal.clear();
+      if self.render.enabled {
+          self.render();
+      }
+  }
+
+  fn apply_params(&mut self, params: LifeParams) {
+      let channel_changed = params.life_channel != self.params.life_channel;
+      self.params = params;
+      if channel_changed {
+          self.reload_binary();
+      }
+      self.tiles.mark_all();
+      self.cycles.invalidate();
+  }
+
+  /// Adopt the rule of a loaded `pattern`, if it has one, and place it.
+  fn load_parsed(&mut self, pattern: &Pattern, x: u32, y: u32) {
+      if let Some(rule) = pattern.rule {
+          self.params.rule = rule;
+          self.tiles.mark_all();
+          self.cycles.invalidate();
+      }
+      self.place_pattern(pattern, x, y);
+  }
+
+  /// Draw `pattern` with its top-left corner at `(x, y)`, wrapping around
+  /// the edges.  Its bounding box is cleared first and the whole placement
+  /// is one undoable edit.
+  pub fn place_pattern(&mut self, pattern: &Pattern, x: u32, y: u32) {
+      if self.width == 0 || self.height == 0 {
+          return;
+      }
+      let color = self.pattern_color.unwrap_or_else(|| binary_born_cell(self.params.life_channel));
+      let wrap = |base: u32, offset: u32, size: u32| ((base as u64 + offset as u64) % size as u64) as u32;
+
+      self.journal.begin(EditKind::Stamp);
+      for row in 0..pattern.height.min(self.height) {
+          for col in 0..pattern.width.min(self.width) {
+              let (r, c) = (wrap(y, row, self.height), wrap(x, col, self.width));
+              self.set_cell(r, c, 0, 0, 0, 0);
+          }
+      }
+      for &(col, row) in &pattern.cells {
+          let (r, c) = (wrap(y, row, self.height), wrap(x, col, self.width));
+          self.set_cell(r, c, color.hue, color.saturation, color.luminance, color.alpha);
+      }
+      self.journal.end();
+  }
+
+  /// Place `image` per `options`; see `import_image`.
+  fn stamp_image(&mut self, image: &RgbaImage, options: &ImportOptions) {
+      let Some((x0, y0, w, h)) = options.region(self.width, self.height) else { return };
+      let placed = import::place(image, w, h, options);
+      let live = import::dither(&placed, options.dither, x0, y0);
+      let channel = self.params.life_channel;
+
+      self.journal.begin(EditKind::Stamp);
+      for (i, p) in placed.pixels.chunks_exact(4).enumerate() {
+          if p[3] == 0 {
+              continue;
+          }
+          let mut cell = Individual::from_rgba(p[0], p[1], p[2], p[3]);
+          if let Some(live) = &live {
+              let value = if live[i] { 255 } else { 0 };
+              match channel {
+                  LifeChannel::Hue => cell.hue = value,
+                  LifeChannel::Saturation => cell.saturation = value,
+                  LifeChannel::Luminance => cell.luminance = value,
+                  LifeChannel::Alpha => cell.alpha = value,
+              }
+          }
+          let (row, col) = (y0 + i as u32 / w, x0 + i as u32 % w);
+          self.set_cell(row, col, cell.hue, cell.saturation, cell.luminance, cell.alpha);
+      }
+      self.journal.end();
+  }
+
+  /// The live cells of the `w` x `h` region at `(x, y)`, wrapping around the
+  /// edges, tagged with the current rule.
+  pub fn extract_pattern(&self, x: u32, y: u32, w: u32, h: u32) -> Pattern {
+      let mut pattern = Pattern { width: w, height: h, cells: Vec::new(), rule: Some(self.params.rule) };
+      if self.width == 0 || self.height == 0 {
+          return pattern;
+      }
+      let channel = self.params.life_channel;
+      for row in 0..h {
+          for col in 0..w {
+              let r = ((y as u64 + row as u64) % self.height as u64) as u32;
+              let c = ((x as u64 + col as u64) % self.width as u64) as u32;
+              if self.cells[self.index(r, c)].activity_value(channel) > 0 {
+                  pattern.cells.push((col, row));
+              }
+          }
+      }
+      pattern
+  }
+}
+
+// Test accessors outside wasm_bindgen
+impl Universe {
+
   #[cfg(test)]
   pub fn brush_state(&self) -> &BrushState { &self.brush_state }
 }
@@ -736,7 +2241,7 @@ pub fn median_from_histogram(hist: &[usize; 256], total: usize) -> f32 {
     0.0
 }
 
-#[cfg(feature = "console_error_panic_hook")]
+#[cfg(all(feature = "wasm", feature = "console_error_panic_hook"))]
 #[wasm_bindgen(start)]
 pub fn start() {
   const VERSION: &str = env!("RCA_VERSION");
@@ -746,9 +2251,12 @@ pub fn start() {
   log::info!("RCA v{} ({})", VERSION, COMMIT);
 }
 
-#[cfg(feature = "wee_alloc")]
+// Only for the wasm build, where its size matters; native builds keep the
+// system allocator
+#[cfg(all(feature = "wee_alloc", target_arch = "wasm32"))]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 #[cfg(test)]
+#[allow(clippy::module_inception, clippy::assertions_on_constants)]
 mod tests;
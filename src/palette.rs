@@ -0,0 +1,203 @@
+//! Colour maps for the framebuffer: 256-entry gradients indexed by one
+//! channel of a cell.
+//!
+//! User palettes are JSON, either a bare array of stops or an object with
+//! an optional `name` and a `stops` array:
+//!
+//! ```text
+//! {"name": "fire", "stops": ["#000", {"at": 0.7, "color": "#ff0000"}, [255, 255, 160]]}
+//! ```
+//!
+//! A stop is a colour (`#rgb`, `#rrggbb`, `#rrggbbaa` or an `[r, g, b]` /
+//! `[r, g, b, a]` array) or `{at, color}` with a position in `0.0..=1.0`.
+//! As in CSS gradients, the first and last stops default to 0 and 1 and
+//! stops without a position are spread evenly between their neighbours, so
+//! a list of 256 colours maps one-to-one onto the entries.
+
+use serde::Deserialize;
+
+/// Named scientific palettes, each sampled at nine evenly spaced stops.
+const NAMED: &[(&str, [u32; 9])] = &[
+    ("viridis", [0x440154, 0x482878, 0x3e4989, 0x31688e, 0x26828e, 0x1f9e89, 0x35b779, 0x6ece58, 0xfde725]),
+    ("magma", [0x000004, 0x1c1044, 0x4f127b, 0x812581, 0xb5367a, 0xe55064, 0xfb8761, 0xfec287, 0xfcfdbf]),
+    ("inferno", [0x000004, 0x1f0c48, 0x550f6d, 0x88226a, 0xba3655, 0xe35933, 0xf98e09, 0xf9cb35, 0xfcffa4]),
+    ("plasma", [0x0d0887, 0x4c02a1, 0x7e03a8, 0xa92395, 0xcc4778, 0xe56b5d, 0xf89540, 0xfdc527, 0xf0f921]),
+    ("cividis", [0x00224e, 0x123570, 0x3b496c, 0x575d6d, 0x707173, 0x8a8678, 0xa59c74, 0xc3b369, 0xfee838]),
+    ("turbo", [0x30123b, 0x4662d7, 0x36aaf9, 0x1ae4b6, 0x72fe5e, 0xc8ef34, 0xfaba39, 0xf66b19, 0x7a0403]),
+    ("grayscale", [0x000000, 0x202020, 0x404040, 0x606060, 0x808080, 0x9f9f9f, 0xbfbfbf, 0xdfdfdf, 0xffffff]),
+];
+
+/// Names accepted by `Palette::named`.
+pub const PALETTE_NAMES: &[&str] = &["viridis", "magma", "inferno", "plasma", "cividis", "turbo", "grayscale"];
+
+/// A 256-entry RGBA lookup table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: [[u8; 4]; 256],
+}
+
+impl Palette {
+    /// A built-in palette by name, ignoring case.
+    pub fn named(name: &str) -> Option<Palette> {
+        let (name, stops) = NAMED.iter().find(|(n, _)| n.eq_ignore_ascii_case(name))?;
+        let stops: Vec<(f32, [u8; 4])> = stops
+            .iter()
+            .enumerate()
+            .map(|(i, &rgb)| {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                (i as f32 / (stops.len() - 1) as f32, [r, g, b, 255])
+            })
+            .collect();
+        Some(Palette { name: name.to_string(), colors: gradient(&stops) })
+    }
+
+    /// Interpolate between `(position, rgba)` stops, positions ascending in
+    /// `0.0..=1.0`.
+    pub fn from_stops(name: &str, stops: &[(f32, [u8; 4])]) -> Result<Palette, String> {
+        if stops.is_empty() {
+            return Err("palette needs at least one stop".to_string());
+        }
+        for pair in stops.windows(2) {
+            if pair[1].0 < pair[0].0 {
+                return Err(format!("palette stop at {} comes after one at {}", pair[1].0, pair[0].0));
+            }
+        }
+        if let Some(&(at, _)) = stops.iter().find(|(at, _)| !(0.0..=1.0).contains(at)) {
+            return Err(format!("palette stop position {} is outside 0..1", at));
+        }
+        Ok(Palette { name: name.to_string(), colors: gradient(stops) })
+    }
+
+    /// Exactly 256 entries given as 1024 RGBA bytes.
+    pub fn from_rgba(name: &str, bytes: &[u8]) -> Result<Palette, String> {
+        if bytes.len() != 256 * 4 {
+            return Err(format!("palette needs 1024 RGBA bytes, got {}", bytes.len()));
+        }
+        let mut colors = [[0u8; 4]; 256];
+        for (color, chunk) in colors.iter_mut().zip(bytes.chunks_exact(4)) {
+            color.copy_from_slice(chunk);
+        }
+        Ok(Palette { name: name.to_string(), colors })
+    }
+
+    /// A user palette from JSON (see the module docs).  Unnamed palettes
+    /// are called "custom".
+    pub fn from_json(json: &str) -> Result<Palette, String> {
+        let parsed: PaletteJson = serde_json::from_str(json).map_err(|e| format!("invalid palette JSON: {}", e))?;
+        let (name, stops) = match parsed {
+            PaletteJson::Stops(stops) => (None, stops),
+            PaletteJson::Object { name, stops } => (name, stops),
+        };
+
+        let mut positions = Vec::with_capacity(stops.len());
+        let mut colors = Vec::with_capacity(stops.len());
+        for stop in stops {
+            let (at, color) = match stop {
+                StopJson::Color(color) => (None, color),
+                StopJson::At { at, color } => (Some(at), color),
+            };
+            positions.push(at);
+            colors.push(color.to_rgba()?);
+        }
+        let positions = spread_positions(&positions);
+        let stops: Vec<(f32, [u8; 4])> = positions.into_iter().zip(colors).collect();
+        Palette::from_stops(name.as_deref().unwrap_or("custom"), &stops)
+    }
+
+    /// The 256 entries as 1024 RGBA bytes, e.g. for a shader lookup texture.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.colors.concat()
+    }
+}
+
+/// Fill in missing stop positions the way CSS gradients do.
+fn spread_positions(positions: &[Option<f32>]) -> Vec<f32> {
+    let mut out: Vec<Option<f32>> = positions.to_vec();
+    let Some(last) = out.len().checked_sub(1) else { return Vec::new() };
+    out[0] = out[0].or(Some(0.0));
+    out[last] = out[last].or(Some(1.0));
+
+    let mut i = 0;
+    while i < last {
+        let start = i;
+        i += 1;
+        while out[i].is_none() {
+            i += 1;
+        }
+        let (from, to) = (out[start].unwrap(), out[i].unwrap());
+        let span = (i - start) as f32;
+        for (k, slot) in out[start + 1..i].iter_mut().enumerate() {
+            *slot = Some(from + (to - from) * (k + 1) as f32 / span);
+        }
+    }
+    out.into_iter().map(Option::unwrap).collect()
+}
+
+/// Sample `stops` at 256 evenly spaced positions.
+fn gradient(stops: &[(f32, [u8; 4])]) -> [[u8; 4]; 256] {
+    let mut colors = [[0u8; 4]; 256];
+    for (i, color) in colors.iter_mut().enumerate() {
+        let t = i as f32 / 255.0;
+        let upper = stops.iter().position(|&(at, _)| at >= t).unwrap_or(stops.len() - 1);
+        let lower = upper.saturating_sub(1);
+        let ((a, from), (b, to)) = (stops[lower], stops[upper]);
+        let f = if b > a { ((t - a) / (b - a)).clamp(0.0, 1.0) } else { 1.0 };
+        for ((c, &x), &y) in color.iter_mut().zip(&from).zip(&to) {
+            *c = (x as f32 + (y as f32 - x as f32) * f).round() as u8;
+        }
+    }
+    colors
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PaletteJson {
+    Stops(Vec<StopJson>),
+    Object {
+        #[serde(default)]
+        name: Option<String>,
+        stops: Vec<StopJson>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StopJson {
+    Color(ColorJson),
+    At { at: f32, color: ColorJson },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorJson {
+    Hex(String),
+    Components(Vec<u8>),
+}
+
+impl ColorJson {
+    fn to_rgba(&self) -> Result<[u8; 4], String> {
+        match self {
+            ColorJson::Components(c) => match c[..] {
+                [r, g, b] => Ok([r, g, b, 255]),
+                [r, g, b, a] => Ok([r, g, b, a]),
+                _ => Err(format!("palette colour needs 3 or 4 components, got {}", c.len())),
+            },
+            ColorJson::Hex(text) => {
+                let invalid = || format!("invalid palette colour '{}'", text);
+                let digits = text.strip_prefix('#').ok_or_else(invalid)?;
+                if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(invalid());
+                }
+                let nibble = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).unwrap() * 17;
+                let byte = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+                match digits.len() {
+                    3 => Ok([nibble(0), nibble(1), nibble(2), 255]),
+                    6 => Ok([byte(0), byte(2), byte(4), 255]),
+                    8 => Ok([byte(0), byte(2), byte(4), byte(6)]),
+                    _ => Err(invalid()),
+                }
+            }
+        }
+    }
+}
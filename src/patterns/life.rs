@@ -0,0 +1,88 @@
+use super::{row_text, rows, Pattern};
+
+/// Conway's rule, implied by Life 1.05 files with `#N`.
+const CONWAY: u32 = 0x1808;
+
+/// Parse a Life 1.06 file: a `#Life 1.06` header followed by one `x y`
+/// coordinate pair per live cell.
+pub fn parse_life106(text: &str) -> Result<Pattern, String> {
+    let mut points = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace().map(str::parse::<i64>);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => points.push((x, y)),
+            _ => return Err(format!("invalid Life 1.06 coordinate line '{}'", line)),
+        }
+    }
+    Pattern::from_points(&points, None)
+}
+
+pub fn write_life106(pattern: &Pattern) -> String {
+    let mut out = String::from("#Life 1.06\n");
+    for &(x, y) in &pattern.cells {
+        out.push_str(&format!("{} {}\n", x, y));
+    }
+    out
+}
+
+/// Parse a Life 1.05 file: blocks of `.`/`*` rows, each placed by the
+/// `#P x y` line before it.  `#N` selects Conway's rule and `#R` gives a
+/// survival/birth rule such as `23/3`.
+pub fn parse_life105(text: &str) -> Result<Pattern, String> {
+    let mut points = Vec::new();
+    let mut rule = None;
+    let (mut x0, mut y) = (0i64, 0i64);
+    for line in text.lines().map(str::trim) {
+        if let Some(directive) = line.strip_prefix('#') {
+            let mut parts = directive.split_whitespace();
+            match parts.next() {
+                Some("N") => rule = Some(CONWAY),
+                Some("R") => {
+                    let notation = parts.next().unwrap_or("");
+                    rule = Some(crate::parse_rule(notation)?);
+                }
+                Some("P") => {
+                    let mut coord = || parts.next().and_then(|v| v.parse::<i64>().ok());
+                    let (Some(px), Some(py)) = (coord(), coord()) else {
+                        return Err(format!("invalid Life 1.05 block position '{}'", line));
+                    };
+                    x0 = px;
+                    y = py;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        for (dx, c) in line.chars().enumerate() {
+            match c {
+                '*' => points.push((x0 + dx as i64, y)),
+                '.' => {}
+                _ => return Err(format!("unexpected '{}' in Life 1.05 block", c)),
+            }
+        }
+        y += 1;
+    }
+    Pattern::from_points(&points, rule)
+}
+
+pub fn write_life105(pattern: &Pattern) -> String {
+    let mut out = String::from("#Life 1.05\n");
+    match pattern.rule {
+        Some(CONWAY) | None => out.push_str("#N\n"),
+        Some(rule) => {
+            let notation = crate::format_rule(rule);
+            let (birth, survival) = notation.split_once('/').unwrap_or(("B", "S"));
+            out.push_str(&format!("#R {}/{}\n", &survival[1..], &birth[1..]));
+        }
+    }
+    out.push_str("#P 0 0\n");
+    for row in rows(pattern) {
+        let line = row_text(&row, '.', '*');
+        out.push_str(if line.is_empty() { "." } else { &line });
+        out.push('\n');
+    }
+    out
+}
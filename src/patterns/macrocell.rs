@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use super::{Pattern, MAX_PATTERN_CELLS};
+use crate::rule::{format_rule, parse_rule};
+
+/// Level of the 8x8 leaf nodes written as `.`/`*` rows.
+const LEAF_LEVEL: u32 = 3;
+
+/// A node of a macrocell quadtree.  Index 0 in the file is the empty node
+/// of whatever level is needed.
+enum Node {
+    /// 8x8 leaf: live cells as `(x, y)`.
+    Leaf(Vec<(u8, u8)>),
+    /// Level-1 node of a multi-state file; children are cell states.
+    States([u32; 4]),
+    Branch { level: u32, children: [usize; 4] },
+}
+
+impl Node {
+    fn level(&self) -> u32 {
+        match self {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::States(_) => 1,
+            Node::Branch { level, .. } => *level,
+        }
+    }
+}
+
+/// Parse a Golly macrocell (`.mc`) file.  Two-state leaves and multi-state
+/// level-1 nodes are both accepted; any non-zero state counts as live.
+pub fn parse_macrocell(text: &str) -> Result<Pattern, String> {
+    // Index 0 stands for the empty node
+    let mut nodes: Vec<Node> = vec![Node::Leaf(Vec::new())];
+    // Live cells under each node; shared subtrees make this exponential in
+    // the file's length, so it is checked before anything is expanded
+    let mut counts: Vec<u64> = vec![0];
+    let mut rule = None;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('[') {
+            continue;
+        }
+        if let Some(directive) = line.strip_prefix('#') {
+            if let Some(notation) = directive.strip_prefix('R') {
+                rule = parse_rule(notation.trim()).ok();
+            }
+            continue;
+        }
+        if line.starts_with(['.', '*', '$']) {
+            let mut cells = Vec::new();
+            let (mut x, mut y) = (0u8, 0u8);
+            for c in line.chars() {
+                match c {
+                    '.' => x += 1,
+                    '*' if y < 8 => {
+                        cells.push((x, y));
+                        x += 1;
+                    }
+                    '$' => {
+                        y += 1;
+                        x = 0;
+                    }
+                    _ => return Err(format!("unexpected '{}' in macrocell leaf", c)),
+                }
+                if x > 8 || y > 8 {
+                    return Err(format!("macrocell leaf '{}' exceeds 8x8", line));
+                }
+            }
+            counts.push(cells.len() as u64);
+            nodes.push(Node::Leaf(cells));
+            continue;
+        }
+
+        let fields: Vec<u64> = line
+            .split_whitespace()
+            .map(|f| f.parse().map_err(|_| format!("invalid macrocell node '{}'", line)))
+            .collect::<Result<_, _>>()?;
+        let [level, nw, ne, sw, se] = fields[..] else {
+            return Err(format!("invalid macrocell node '{}'", line));
+        };
+        let level = level as u32;
+        if level == 1 {
+            let states = [nw as u32, ne as u32, sw as u32, se as u32];
+            counts.push(states.iter().filter(|&&state| state != 0).count() as u64);
+            nodes.push(Node::States(states));
+            continue;
+        }
+        if !(2..64).contains(&level) {
+            return Err(format!("unsupported macrocell level {}", level));
+        }
+        let children = [nw, ne, sw, se].map(|c| c as usize);
+        for &child in &children {
+            if child >= nodes.len() {
+                return Err(format!("macrocell node refers to undefined node {}", child));
+            }
+            if child != 0 && nodes[child].level() != level - 1 {
+                return Err(format!(
+                    "macrocell node of level {} has child {} of level {}",
+                    level,
+                    child,
+                    nodes[child].level()
+                ));
+            }
+        }
+        counts.push(children.iter().fold(0u64, |sum, &child| sum.saturating_add(counts[child])));
+        nodes.push(Node::Branch { level, children });
+    }
+
+    if nodes.len() == 1 {
+        return Ok(Pattern { rule, ..Pattern::default() });
+    }
+
+    let root = nodes.len() - 1;
+    if counts[root] > MAX_PATTERN_CELLS {
+        return Err(format!("macrocell pattern has more than {} live cells", MAX_PATTERN_CELLS));
+    }
+
+    // Walk from the root, skipping empty subtrees
+    let mut points = Vec::with_capacity(counts[root] as usize);
+    let mut stack = vec![(root, 0i64, 0i64)];
+    while let Some((index, x, y)) = stack.pop() {
+        if counts[index] == 0 {
+            continue;
+        }
+        match &nodes[index] {
+            Node::Leaf(cells) => {
+                points.extend(cells.iter().map(|&(cx, cy)| (x + cx as i64, y + cy as i64)));
+            }
+            Node::States(states) => {
+                for (q, &state) in states.iter().enumerate() {
+                    if state != 0 {
+                        points.push((x + (q & 1) as i64, y + (q >> 1) as i64));
+                    }
+                }
+            }
+            Node::Branch { level, children } => {
+                let half = 1i64 << (level - 1);
+                for (q, &child) in children.iter().enumerate() {
+                    stack.push((child, x + (q & 1) as i64 * half, y + (q >> 1) as i64 * half));
+                }
+            }
+        }
+    }
+    Pattern::from_points(&points, rule)
+}
+
+/// Write `pattern` as a two-state macrocell file with 8x8 leaves.  Repeated
+/// subtrees are written once.
+pub fn write_macrocell(pattern: &Pattern) -> String {
+    let mut out = String::from("[M2] (ca)\n");
+    if let Some(rule) = pattern.rule {
+        out.push_str(&format!("#R {}\n", format_rule(rule)));
+    }
+    if pattern.cells.is_empty() {
+        return out;
+    }
+
+    let size = pattern.width.max(pattern.height).max(8).next_power_of_two();
+    let level = size.trailing_zeros();
+    let mut cells = pattern.cells.clone();
+    cells.sort_unstable();
+    cells.dedup();
+
+    let mut writer = Writer { lines: Vec::new(), ids: HashMap::new() };
+    writer.build(&cells, 0, 0, level);
+    for line in writer.lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+struct Writer {
+    lines: Vec<String>,
+    /// Node line to its 1-based id, so identical subtrees are shared.
+    ids: HashMap<String, usize>,
+}
+
+impl Writer {
+    /// Emit the node covering the `2^level` square at `(x, y)` holding
+    /// `cells` (sorted), returning its id or 0 if empty.
+    fn build(&mut self, cells: &[(u32, u32)], x: u32, y: u32, level: u32) -> usize {
+        if cells.is_empty() {
+            return 0;
+        }
+        let line = if level == LEAF_LEVEL {
+            let mut grid = [[false; 8]; 8];
+            for &(cx, cy) in cells {
+                grid[(cy - y) as usize][(cx - x) as usize] = true;
+            }
+            let mut line = String::new();
+            for row in grid {
+                let width = row.iter().rposition(|&live| live).map_or(0, |last| last + 1);
+                line.extend(row[..width].iter().map(|&live| if live { '*' } else { '.' }));
+                line.push('$');
+            }
+            line
+        } else {
+            let half = 1u32 << (level - 1);
+            let mut children = [0usize; 4];
+            for (q, child) in children.iter_mut().enumerate() {
+                let (qx, qy) = (x + (q as u32 & 1) * half, y + (q as u32 >> 1) * half);
+                let inside: Vec<(u32, u32)> = cells
+                    .iter()
+                    .copied()
+                    .filter(|&(cx, cy)| cx >= qx && cx < qx + half && cy >= qy && cy < qy + half)
+                    .collect();
+                *child = self.build(&inside, qx, qy, level - 1);
+            }
+            format!("{} {} {} {} {}", level, children[0], children[1], children[2], children[3])
+        };
+        if let Some(&id) = self.ids.get(&line) {
+            return id;
+        }
+        self.lines.push(line.clone());
+        self.ids.insert(line, self.lines.len());
+        self.lines.len()
+    }
+}
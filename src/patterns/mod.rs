@@ -0,0 +1,139 @@
+//! Readers and writers for the common Life pattern exchange formats.
+//!
+//! Every format is read into a `Pattern`, a sparse list of live cells that
+//! `Universe::place_pattern` draws with the universe's pattern colour.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+mod life;
+mod macrocell;
+mod plaintext;
+mod rle;
+
+pub use life::{parse_life105, parse_life106, write_life105, write_life106};
+pub use macrocell::{parse_macrocell, write_macrocell};
+pub use plaintext::{parse_plaintext, write_plaintext};
+pub use rle::{parse_rle, write_rle};
+
+/// Most live cells a parsed pattern may have, so a small file can't expand
+/// into more than memory holds.
+pub(crate) const MAX_PATTERN_CELLS: u64 = 1 << 24;
+
+/// A two-state pattern: the coordinates of its live cells within a
+/// `width` x `height` bounding box, and the rule it was written for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    /// Live cells as `(x, y)`, row-major.
+    pub cells: Vec<(u32, u32)>,
+    pub rule: Option<u32>,
+}
+
+impl Pattern {
+    /// Build a pattern from cells at arbitrary, possibly negative,
+    /// coordinates by shifting them into their bounding box.
+    pub fn from_points(points: &[(i64, i64)], rule: Option<u32>) -> Result<Pattern, String> {
+        let Some(&(x0, y0)) = points.first() else {
+            return Ok(Pattern { rule, ..Pattern::default() });
+        };
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (x0, y0, x0, y0);
+        for &(x, y) in points {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let span = |lo: i64, hi: i64| {
+            u32::try_from(hi as i128 - lo as i128 + 1).map_err(|_| "pattern is too large".to_string())
+        };
+        let (width, height) = (span(min_x, max_x)?, span(min_y, max_y)?);
+        // Both spans fit in u32, so the offsets do too
+        let mut cells: Vec<(u32, u32)> = points
+            .iter()
+            .map(|&(x, y)| (x.wrapping_sub(min_x) as u32, y.wrapping_sub(min_y) as u32))
+            .collect();
+        cells.sort_by_key(|&(x, y)| (y, x));
+        cells.dedup();
+        Ok(Pattern { width, height, cells, rule })
+    }
+}
+
+/// Pattern file formats understood by `parse_pattern` and `write_pattern`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PatternFormat {
+    Rle,
+    /// `.cells`: rows of `.` and `O`.
+    Plaintext,
+    Life105,
+    Life106,
+    /// Golly `.mc` quadtree.
+    Macrocell,
+}
+
+/// Guess the format of `text` from its header.
+pub fn detect_format(text: &str) -> PatternFormat {
+    let first = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    if first.starts_with("[M2]") {
+        PatternFormat::Macrocell
+    } else if first.starts_with("#Life 1.06") {
+        PatternFormat::Life106
+    } else if first.starts_with("#Life 1.05") {
+        PatternFormat::Life105
+    } else if first.starts_with('!') || first.chars().all(|c| matches!(c, '.' | 'O' | '*')) {
+        PatternFormat::Plaintext
+    } else {
+        PatternFormat::Rle
+    }
+}
+
+/// Read `text` in whichever format its header indicates.
+pub fn parse_pattern(text: &str) -> Result<Pattern, String> {
+    match detect_format(text) {
+        PatternFormat::Rle => parse_rle(text),
+        PatternFormat::Plaintext => parse_plaintext(text),
+        PatternFormat::Life105 => parse_life105(text),
+        PatternFormat::Life106 => parse_life106(text),
+        PatternFormat::Macrocell => parse_macrocell(text),
+    }
+}
+
+pub fn write_pattern(pattern: &Pattern, format: PatternFormat) -> String {
+    match format {
+        PatternFormat::Rle => write_rle(pattern),
+        PatternFormat::Plaintext => write_plaintext(pattern),
+        PatternFormat::Life105 => write_life105(pattern),
+        PatternFormat::Life106 => write_life106(pattern),
+        PatternFormat::Macrocell => write_macrocell(pattern),
+    }
+}
+
+/// Live cells of `pattern` grouped by row, each row sorted by column.
+fn rows(pattern: &Pattern) -> Vec<Vec<u32>> {
+    let mut rows = vec![Vec::new(); pattern.height as usize];
+    for &(x, y) in &pattern.cells {
+        if let Some(row) = rows.get_mut(y as usize) {
+            row.push(x);
+        }
+    }
+    for row in rows.iter_mut() {
+        row.sort_unstable();
+        row.dedup();
+    }
+    rows
+}
+
+/// Draw `row` of live columns as `dead`/`live` characters without trailing
+/// dead cells.
+fn row_text(row: &[u32], dead: char, live: char) -> String {
+    let mut line = String::new();
+    for &x in row {
+        while (line.len() as u32) < x {
+            line.push(dead);
+        }
+        line.push(live);
+    }
+    line
+}
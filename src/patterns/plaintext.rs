@@ -0,0 +1,39 @@
+use super::{row_text, rows, Pattern};
+
+/// Parse a plaintext `.cells` pattern: `!` comment lines, then one line per
+/// row with `.` for dead and `O` (or `*`) for live cells.
+pub fn parse_plaintext(text: &str) -> Result<Pattern, String> {
+    let mut lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.starts_with('!'))
+        .map(str::trim_end)
+        .collect();
+    // Trailing blank lines are formatting, not empty rows
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    let mut cells = Vec::new();
+    let mut width = 0u32;
+    for (y, line) in lines.iter().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                'O' | '*' => cells.push((x as u32, y as u32)),
+                '.' => {}
+                _ => return Err(format!("unexpected '{}' in plaintext row {}", c, y + 1)),
+            }
+        }
+        width = width.max(line.chars().count() as u32);
+    }
+    Ok(Pattern { width, height: lines.len() as u32, cells, rule: None })
+}
+
+pub fn write_plaintext(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    for row in rows(pattern) {
+        let line = row_text(&row, '.', 'O');
+        out.push_str(if line.is_empty() { "." } else { &line });
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,159 @@
+use super::{Pattern, MAX_PATTERN_CELLS};
+use crate::rule::{format_rule, parse_rule};
+
+/// Longest line `write_rle` produces, as recommended by the format.
+const LINE_LIMIT: usize = 70;
+
+/// Parse a run-length encoded pattern.
+///
+/// `#` comment lines are skipped.  The `x = .., y = .., rule = ..` header is
+/// optional; a rule that is not plain birth/survival (e.g. Generations or a
+/// bounded grid suffix we can't honour) is ignored.  Multi-state letters
+/// (`.`, `A`..`X`, `pA`..`yO`) are accepted and every non-zero state counts
+/// as live.
+pub fn parse_rle(text: &str) -> Result<Pattern, String> {
+    let mut pattern = Pattern::default();
+    let mut body = String::new();
+    let mut header_seen = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !header_seen && body.is_empty() && line.starts_with('x') {
+            header_seen = true;
+            parse_header(line, &mut pattern)?;
+            continue;
+        }
+        body.push_str(line);
+        if line.contains('!') {
+            break;
+        }
+    }
+
+    let (mut x, mut y) = (0u32, 0u32);
+    let mut width = 0u32;
+    let mut run: Option<u32> = None;
+    let mut prefix: Option<char> = None;
+
+    for c in body.chars() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if let Some(p) = prefix.take() {
+            if !c.is_ascii_uppercase() {
+                return Err(format!("expected a state letter after '{}', found '{}'", p, c));
+            }
+        }
+        match c {
+            '0'..='9' => {
+                let digit = c as u32 - '0' as u32;
+                let n = run.unwrap_or(0).checked_mul(10).and_then(|n| n.checked_add(digit));
+                run = Some(n.ok_or("run count overflows")?);
+                continue;
+            }
+            'p'..='y' => {
+                prefix = Some(c);
+                continue;
+            }
+            '!' => break,
+            _ => {}
+        }
+        let count = run.take().unwrap_or(1);
+        let advance = |pos: u32| pos.checked_add(count).ok_or_else(|| format!("run of {} '{}' overflows", count, c));
+        match c {
+            'b' | '.' => x = advance(x)?,
+            'o' | 'A'..='X' => {
+                let end = advance(x)?;
+                if pattern.cells.len() as u64 + count as u64 > MAX_PATTERN_CELLS {
+                    return Err(format!("RLE pattern has more than {} live cells", MAX_PATTERN_CELLS));
+                }
+                pattern.cells.extend((x..end).map(|col| (col, y)));
+                x = end;
+            }
+            '$' => {
+                y = advance(y)?;
+                x = 0;
+            }
+            _ => return Err(format!("unexpected '{}' in RLE data", c)),
+        }
+        width = width.max(x);
+    }
+
+    if !header_seen {
+        pattern.width = width;
+        pattern.height = pattern.cells.last().map_or(0, |&(_, row)| row + 1);
+    }
+    Ok(pattern)
+}
+
+fn parse_header(line: &str, pattern: &mut Pattern) -> Result<(), String> {
+    for field in line.split(',') {
+        let Some((key, value)) = field.split_once('=') else {
+            return Err(format!("malformed RLE header field '{}'", field.trim()));
+        };
+        let value = value.trim();
+        match key.trim() {
+            "x" => pattern.width = value.parse().map_err(|_| format!("invalid width '{}'", value))?,
+            "y" => pattern.height = value.parse().map_err(|_| format!("invalid height '{}'", value))?,
+            "rule" => {
+                let rule = value.split(':').next().unwrap_or(value);
+                pattern.rule = parse_rule(rule).ok();
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Write `pattern` as RLE, with a header giving its size and rule.
+pub fn write_rle(pattern: &Pattern) -> String {
+    let mut out = format!("x = {}, y = {}", pattern.width, pattern.height);
+    if let Some(rule) = pattern.rule {
+        out.push_str(&format!(", rule = {}", format_rule(rule)));
+    }
+    out.push('\n');
+
+    let mut cells = pattern.cells.clone();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+
+    // Runs of (count, tag); trailing dead cells are never written
+    let mut runs: Vec<(u32, char)> = Vec::new();
+    let mut push = |count: u32, tag: char| {
+        if count == 0 {
+            return;
+        }
+        match runs.last_mut() {
+            Some((n, t)) if *t == tag => *n += count,
+            _ => runs.push((count, tag)),
+        }
+    };
+    let (mut x, mut y) = (0u32, 0u32);
+    for &(cx, cy) in &cells {
+        if cy > y {
+            push(cy - y, '$');
+            x = 0;
+            y = cy;
+        }
+        push(cx - x, 'b');
+        push(1, 'o');
+        x = cx + 1;
+    }
+    push(1, '!');
+
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let item = if count == 1 { tag.to_string() } else { format!("{}{}", count, tag) };
+        if line.len() + item.len() > LINE_LIMIT {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        line.push_str(&item);
+    }
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
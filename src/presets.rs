@@ -0,0 +1,102 @@
+use serde::Serialize;
+
+use crate::{LifeChannel, LifeParams};
+
+/// A named, ready-to-run parameter set.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: LifeParams,
+    /// Seed that gives a good-looking `randomize` with these params.
+    pub seed: u64,
+}
+
+const fn params(
+    rule: u32,
+    decay_step: u8,
+    recovery_step: u8,
+    factors: [f32; 7],
+    life_channel: LifeChannel,
+) -> LifeParams {
+    let [
+        sat_recovery_factor,
+        sat_decay_factor,
+        lum_decay_factor,
+        life_decay_factor,
+        sat_ghost_factor,
+        hue_drift_strength,
+        hue_lerp_factor,
+    ] = factors;
+    LifeParams {
+        rule,
+        decay_step,
+        recovery_step,
+        sat_recovery_factor,
+        sat_decay_factor,
+        lum_decay_factor,
+        life_decay_factor,
+        sat_ghost_factor,
+        hue_drift_strength,
+        hue_lerp_factor,
+        life_channel,
+    }
+}
+
+/// Built-in presets, listed by `Universe::presets_json` and applied with
+/// `Universe::apply_preset`.
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "default",
+        description: "The stock parameters: B18/S0 with slow fades and gentle hue drift.",
+        params: params(770, 1, 60, [0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1], LifeChannel::Alpha),
+        seed: 1,
+    },
+    Preset {
+        name: "conway",
+        description: "Conway's B3/S23 with cells that die at once.",
+        params: params(0x1808, 255, 60, [0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1], LifeChannel::Alpha),
+        seed: 42,
+    },
+    Preset {
+        name: "conway-ghosts",
+        description: "B3/S23 leaving long, desaturating trails behind moving patterns.",
+        params: params(0x1808, 4, 60, [0.8, 0.6, 0.98, 0.98, 0.7, 0.02, 0.2], LifeChannel::Alpha),
+        seed: 7,
+    },
+    Preset {
+        name: "highlife",
+        description: "B36/S23, home of the replicator.",
+        params: params(0x1848, 255, 60, [0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1], LifeChannel::Alpha),
+        seed: 36,
+    },
+    Preset {
+        name: "day-and-night",
+        description: "B3678/S34678: live and dead regions behave symmetrically.",
+        params: params(0x3B1C8, 255, 60, [0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1], LifeChannel::Alpha),
+        seed: 3678,
+    },
+    Preset {
+        name: "maze",
+        description: "B3/S12345 grows corridors from random soup.",
+        params: params(0x7C08, 255, 60, [0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1], LifeChannel::Alpha),
+        seed: 12345,
+    },
+    Preset {
+        name: "seeds",
+        description: "B2/S: every cell dies each generation; explosive growth.",
+        params: params(0x4, 255, 60, [0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1], LifeChannel::Alpha),
+        seed: 2,
+    },
+    Preset {
+        name: "rainbow",
+        description: "B3/S23 with strong hue drift so colonies shift through the spectrum.",
+        params: params(0x1808, 255, 60, [0.8, 0.6, 0.95, 0.95, 0.9, 0.3, 0.05], LifeChannel::Alpha),
+        seed: 256,
+    },
+];
+
+/// Look up a built-in preset by name, ignoring case.
+pub fn preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,121 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Individual, LifeChannel, Palette};
+
+/// What the framebuffer shows.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenderSource {
+    /// The cells' colours, or a channel through the palette.
+    Cells,
+    /// A heat map of cell ages through the palette (inferno if none is
+    /// set).  Needs age tracking.
+    Age,
+    /// A heat map of recent births and deaths, likewise.  Needs activity
+    /// tracking.
+    Activity,
+}
+
+/// Per-cell data a frame is rendered from.
+pub(crate) struct Layers<'a> {
+    pub cells: &'a [Individual],
+    /// Empty unless age tracking is on.
+    pub ages: &'a [u32],
+    /// Empty unless activity tracking is on.
+    pub activity: &'a [f32],
+}
+
+/// RGBA pixels of the grid, converted the way the client's render shader
+/// does it, for canvas 2D, workers and the CLI.
+pub(crate) struct Framebuffer {
+    pub enabled: bool,
+    /// Keep cell alpha instead of rendering opaque.
+    pub use_alpha: bool,
+    /// Show the raw channels as RGB instead of converting from HSL.
+    pub use_rgb: bool,
+    /// Colour map replacing the HSL conversion, if any.
+    pub palette: Option<Palette>,
+    /// Channel that indexes `palette`.
+    pub palette_channel: LifeChannel,
+    pub source: RenderSource,
+    /// Age shown at the top of the palette; older cells saturate.
+    pub age_range: u32,
+    pub rgba: Vec<u8>,
+    /// Palette behind the heat maps when none is set.
+    heat: Palette,
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            use_alpha: true,
+            use_rgb: false,
+            palette: None,
+            palette_channel: LifeChannel::Alpha,
+            source: RenderSource::Cells,
+            age_range: 255,
+            rgba: Vec::new(),
+            heat: Palette::named("inferno").expect("inferno is built in"),
+        }
+    }
+
+    /// The same settings, with no pixels and not maintained.
+    pub fn detached(&self) -> Self {
+        Self { enabled: false, rgba: Vec::new(), palette: self.palette.clone(), heat: self.heat.clone(), ..*self }
+    }
+
+    /// The pixel for cell `idx`.  Through a palette, `use_alpha` scales the
+    /// entry's alpha by the cell's.
+    #[inline]
+    pub fn pixel(&self, layers: &Layers, idx: usize) -> [u8; 4] {
+        let cell = &layers.cells[idx];
+        let mut color = match (self.source, &self.palette) {
+            (RenderSource::Cells, None) => return cell.to_rgba(self.use_alpha, self.use_rgb),
+            (RenderSource::Cells, Some(palette)) => palette.colors[cell.activity_value(self.palette_channel) as usize],
+            (RenderSource::Age, palette) => {
+                let age = layers.ages.get(idx).copied().unwrap_or(0).min(self.age_range) as u64;
+                let index = age * 255 / self.age_range.max(1) as u64;
+                palette.as_ref().unwrap_or(&self.heat).colors[index as usize]
+            }
+            (RenderSource::Activity, palette) => {
+                let activity = layers.activity.get(idx).copied().unwrap_or(0.0);
+                let index = (activity * 255.0).round().clamp(0.0, 255.0) as usize;
+                palette.as_ref().unwrap_or(&self.heat).colors[index]
+            }
+        };
+        if self.use_alpha {
+            color[3] = ((color[3] as u16 * cell.alpha as u16 + 127) / 255) as u8;
+        }
+        color
+    }
+
+    /// Convert every cell.
+    pub fn render_all(&mut self, layers: &Layers) {
+        let mut rgba = std::mem::take(&mut self.rgba);
+        rgba.resize(layers.cells.len() * 4, 0);
+        for (idx, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&self.pixel(layers, idx));
+        }
+        self.rgba = rgba;
+    }
+
+    /// Convert the cells of one tile, `(col_start, row_start, col_end, row_end)`.
+    pub fn render_region(&mut self, layers: &Layers, width: u32, bounds: (u32, u32, u32, u32)) {
+        if self.rgba.len() != layers.cells.len() * 4 {
+            self.render_all(layers);
+            return;
+        }
+        let mut rgba = std::mem::take(&mut self.rgba);
+        let (x0, y0, x1, y1) = bounds;
+        for row in y0..y1 {
+            let start = (row * width + x0) as usize;
+            let end = (row * width + x1) as usize;
+            for (idx, pixel) in (start..end).zip(rgba[start * 4..end * 4].chunks_exact_mut(4)) {
+                pixel.copy_from_slice(&self.pixel(layers, idx));
+            }
+        }
+        self.rgba = rgba;
+    }
+}
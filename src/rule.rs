@@ -0,0 +1,91 @@
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Parse a birth/survival rule into the 18-bit `LifeParams.rule` mask.
+///
+/// Accepts `B3/S23`, `b3s23`, `B36/S23`, `S23/B3` and the classic
+/// survival-first form `23/3`.  Either half may be empty (`B3/S`).
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn parse_rule(notation: &str) -> Result<u32, String> {
+    let text: String = notation.chars().filter(|c| !c.is_whitespace()).collect();
+    let upper = text.to_ascii_uppercase();
+
+    let (birth, survival) = if upper.contains('B') || upper.contains('S') {
+        let mut birth = None;
+        let mut survival = None;
+        let mut current: Option<char> = None;
+        for c in upper.chars() {
+            match c {
+                'B' | 'S' => {
+                    let slot = if c == 'B' { &mut birth } else { &mut survival };
+                    if slot.is_some() {
+                        return Err(format!("duplicate '{}' in rule '{}'", c, notation));
+                    }
+                    *slot = Some(String::new());
+                    current = Some(c);
+                }
+                '/' => current = None,
+                '0'..='8' => {
+                    let slot = match current {
+                        Some('B') => &mut birth,
+                        Some('S') => &mut survival,
+                        _ => return Err(format!("digit outside B/S section in rule '{}'", notation)),
+                    };
+                    slot.as_mut().unwrap().push(c);
+                }
+                _ => return Err(format!("unexpected '{}' in rule '{}'", c, notation)),
+            }
+        }
+        if birth.is_none() && survival.is_none() {
+            return Err(format!("empty rule '{}'", notation));
+        }
+        (birth.unwrap_or_default(), survival.unwrap_or_default())
+    } else {
+        // Survival/birth, e.g. 23/3 for Conway
+        let mut parts = upper.split('/');
+        let (Some(survival), Some(birth), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("unrecognised rule '{}'", notation));
+        };
+        (birth.to_string(), survival.to_string())
+    };
+
+    let mut rule = 0u32;
+    for (digits, offset) in [(&birth, 0), (&survival, 9)] {
+        for c in digits.chars() {
+            let n = c
+                .to_digit(10)
+                .filter(|&n| n <= 8)
+                .ok_or_else(|| format!("invalid neighbour count '{}' in rule '{}'", c, notation))?;
+            rule |= 1 << (n + offset);
+        }
+    }
+    Ok(rule)
+}
+
+/// Format a `LifeParams.rule` mask as `B.../S...` notation.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn format_rule(rule: u32) -> String {
+    let digits = |offset: u32| -> String {
+        (0..9)
+            .filter(|n| (rule >> (n + offset)) & 1 == 1)
+            .map(|n| char::from(b'0' + n as u8))
+            .collect()
+    };
+    format!("B{}/S{}", digits(0), digits(9))
+}
+
+/// Deserialize a rule given either as a mask or in notation `parse_rule`
+/// understands, for `LifeParams.rule`.
+pub(crate) fn deserialize_rule<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Rule {
+        Mask(u32),
+        Notation(String),
+    }
+    match Rule::deserialize(deserializer)? {
+        Rule::Mask(mask) => Ok(mask),
+        Rule::Notation(text) => parse_rule(&text).map_err(serde::de::Error::custom),
+    }
+}
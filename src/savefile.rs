@@ -0,0 +1,257 @@
+//! The container written by `Universe::save_state`.
+//!
+//! Layout, all integers little-endian:
+//!
+//! ```text
+//! magic      b"RCA\x1a"
+//! version    u16
+//! width      u32
+//! height     u32
+//! params     rule u32, decay_step u8, recovery_step u8, 7 x f32 factors,
+//!            life_channel u8
+//! generation u64
+//! seed       u64
+//! rng state  u64
+//! cells      width * height x (hue, saturation, luminance, alpha)
+//! layers     u16 count, then per layer a 4-byte tag, u32 length and data
+//! checksum   u32 CRC-32 of everything above
+//! ```
+//!
+//! Layers carry optional per-cell data; readers skip tags they don't know.
+
+use crate::{Individual, LifeChannel, LifeParams};
+
+pub(crate) const MAGIC: &[u8; 4] = b"RCA\x1a";
+pub(crate) const VERSION: u16 = 1;
+
+/// Cells drawn since the last generation, one bit each.
+pub(crate) const LAYER_DRAWN: [u8; 4] = *b"DRAW";
+/// Cell ages, a little-endian `u32` each.
+pub(crate) const LAYER_AGES: [u8; 4] = *b"AGES";
+/// Cell activity, a little-endian `f32` each.
+pub(crate) const LAYER_ACTIVITY: [u8; 4] = *b"ACTV";
+
+pub(crate) struct Layer {
+    pub tag: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+pub(crate) struct SaveData {
+    pub width: u32,
+    pub height: u32,
+    pub params: LifeParams,
+    pub generation: u64,
+    pub seed: u64,
+    pub rng_state: u64,
+    pub cells: Vec<Individual>,
+    pub layers: Vec<Layer>,
+}
+
+impl SaveData {
+    pub fn layer(&self, tag: [u8; 4]) -> Option<&[u8]> {
+        self.layers.iter().find(|layer| layer.tag == tag).map(|layer| layer.data.as_slice())
+    }
+}
+
+/// Whether `bytes` starts like a container rather than a legacy raw dump.
+pub(crate) fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Bytes before the cells: magic, version, dimensions, params, generation,
+/// seed and RNG state.
+const HEADER_LEN: usize = 4 + 2 + 4 + 4 + (4 + 1 + 1 + 7 * 4 + 1) + 8 + 8 + 8;
+
+/// Where the cells of `container` lie, if it is long enough to hold them.
+pub(crate) fn cells_range(container: &[u8]) -> Option<std::ops::Range<usize>> {
+    if !is_container(container) || container.len() < HEADER_LEN {
+        return None;
+    }
+    let width = u32::from_le_bytes(container[6..10].try_into().unwrap()) as usize;
+    let height = u32::from_le_bytes(container[10..14].try_into().unwrap()) as usize;
+    let end = width.checked_mul(height)?.checked_mul(4)?.checked_add(HEADER_LEN)?;
+    (end <= container.len()).then_some(HEADER_LEN..end)
+}
+
+pub(crate) fn encode(save: &SaveData) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64 + save.cells.len() * 4);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&save.width.to_le_bytes());
+    out.extend_from_slice(&save.height.to_le_bytes());
+
+    let p = &save.params;
+    out.extend_from_slice(&p.rule.to_le_bytes());
+    out.push(p.decay_step);
+    out.push(p.recovery_step);
+    for factor in [
+        p.sat_recovery_factor,
+        p.sat_decay_factor,
+        p.lum_decay_factor,
+        p.life_decay_factor,
+        p.sat_ghost_factor,
+        p.hue_drift_strength,
+        p.hue_lerp_factor,
+    ] {
+        out.extend_from_slice(&factor.to_le_bytes());
+    }
+    out.push(p.life_channel as u8);
+
+    out.extend_from_slice(&save.generation.to_le_bytes());
+    out.extend_from_slice(&save.seed.to_le_bytes());
+    out.extend_from_slice(&save.rng_state.to_le_bytes());
+    for c in &save.cells {
+        out.extend_from_slice(&[c.hue, c.saturation, c.luminance, c.alpha]);
+    }
+
+    out.extend_from_slice(&(save.layers.len() as u16).to_le_bytes());
+    for layer in &save.layers {
+        out.extend_from_slice(&layer.tag);
+        out.extend_from_slice(&(layer.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&layer.data);
+    }
+
+    let checksum = crc32(&out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+    out
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<SaveData, String> {
+    if !is_container(bytes) {
+        return Err("not a saved universe (bad magic)".to_string());
+    }
+    let Some(body_len) = bytes.len().checked_sub(4) else {
+        return Err("saved universe is truncated".to_string());
+    };
+    let (body, stored) = bytes.split_at(body_len);
+    let stored = u32::from_le_bytes(stored.try_into().unwrap());
+    if crc32(body) != stored {
+        return Err("saved universe is corrupt (checksum mismatch)".to_string());
+    }
+
+    let mut r = Reader { bytes: body, pos: MAGIC.len() };
+    let version = r.u16()?;
+    if version == 0 {
+        return Err("saved universe has invalid format version 0".to_string());
+    }
+    if version > VERSION {
+        return Err(format!("saved universe is format version {}, newer than the supported {}", version, VERSION));
+    }
+    let width = r.u32()?;
+    let height = r.u32()?;
+
+    let rule = r.u32()?;
+    let decay_step = r.u8()?;
+    let recovery_step = r.u8()?;
+    let mut factors = [0f32; 7];
+    for factor in factors.iter_mut() {
+        *factor = f32::from_le_bytes(r.take(4)?.try_into().unwrap());
+    }
+    let life_channel = match r.u8()? {
+        0 => LifeChannel::Hue,
+        1 => LifeChannel::Saturation,
+        2 => LifeChannel::Luminance,
+        3 => LifeChannel::Alpha,
+        other => return Err(format!("saved universe has unknown life channel {}", other)),
+    };
+    let [
+        sat_recovery_factor,
+        sat_decay_factor,
+        lum_decay_factor,
+        life_decay_factor,
+        sat_ghost_factor,
+        hue_drift_strength,
+        hue_lerp_factor,
+    ] = factors;
+    let params = LifeParams {
+        rule,
+        decay_step,
+        recovery_step,
+        sat_recovery_factor,
+        sat_decay_factor,
+        lum_decay_factor,
+        life_decay_factor,
+        sat_ghost_factor,
+        hue_drift_strength,
+        hue_lerp_factor,
+        life_channel,
+    };
+
+    let generation = r.u64()?;
+    let seed = r.u64()?;
+    let rng_state = r.u64()?;
+
+    let too_large = || format!("saved universe of {}x{} cells is too large", width, height);
+    let count = usize::try_from(u64::from(width) * u64::from(height)).map_err(|_| too_large())?;
+    let cell_bytes = r.take(count.checked_mul(4).ok_or_else(too_large)?)?;
+    let cells = cell_bytes
+        .chunks_exact(4)
+        .map(|c| Individual { hue: c[0], saturation: c[1], luminance: c[2], alpha: c[3] })
+        .collect();
+
+    let mut layers = Vec::new();
+    for _ in 0..r.u16()? {
+        let tag: [u8; 4] = r.take(4)?.try_into().unwrap();
+        let len = r.u32()? as usize;
+        layers.push(Layer { tag, data: r.take(len)?.to_vec() });
+    }
+    if r.pos != body.len() {
+        return Err(format!("saved universe has {} unexpected trailing bytes", body.len() - r.pos));
+    }
+
+    Ok(SaveData { width, height, params, generation, seed, rng_state, cells, layers })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err("saved universe is truncated".to_string());
+        };
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE), as used by zip and PNG.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |c, &b| CRC_TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8))
+}
@@ -0,0 +1,165 @@
+//! Transformed, blended stamping for `Universe::draw_stamp`.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{rgb_to_hsl, Individual, LifeChannel};
+
+/// How a stamp pixel combines with the cell under it.  Every mode but
+/// `Replace` mixes its result into the cell's colour by the pixel's alpha.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+    /// Write the pixel's colour and alpha exactly.
+    Replace,
+    /// Paint the colour; the cell becomes fully opaque whatever the
+    /// pixel's alpha, as `draw_stamp_at` always has.
+    Lerp,
+    /// Add hue (wrapping), saturation and luminance; alpha rises towards
+    /// opaque by the pixel's alpha.
+    Add,
+    /// Scale saturation and luminance by the stamp's; hue and alpha stay.
+    Multiply,
+    /// Take only the stamp's hue.
+    Hue,
+    /// Take only the stamp's luminance.
+    Luminance,
+    /// Take only the life channel, from the stamp's value for that channel
+    /// (its luminance when life is alpha), leaving the colour alone.
+    LifeChannel,
+    /// The larger of each channel; alpha rises towards opaque by the
+    /// pixel's alpha.
+    Max,
+}
+
+/// Placement of `Universe::draw_stamp`: a transform about the stamp's
+/// centre, then a blend into the cells.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StampOptions {
+    /// Clockwise, in degrees.
+    pub rotation: f32,
+    /// Cells per stamp pixel.
+    pub scale: f32,
+    /// Mirror left to right, before rotating.
+    pub flip_x: bool,
+    /// Mirror top to bottom, before rotating.
+    pub flip_y: bool,
+    /// Wrap around the torus instead of clipping at the edges.
+    pub wrap: bool,
+    pub blend: BlendMode,
+}
+
+impl Default for StampOptions {
+    fn default() -> Self {
+        Self { rotation: 0.0, scale: 1.0, flip_x: false, flip_y: false, wrap: false, blend: BlendMode::Lerp }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl StampOptions {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(blend: BlendMode) -> StampOptions {
+        StampOptions { blend, ..StampOptions::default() }
+    }
+
+    pub fn set_flip(&mut self, flip_x: bool, flip_y: bool) {
+        (self.flip_x, self.flip_y) = (flip_x, flip_y);
+    }
+}
+
+impl StampOptions {
+    /// Every cell the transformed stamp covers, as an offset from its
+    /// anchor, with the stamp pixel sampled there (nearest).  Transparent
+    /// pixels are skipped.
+    pub(crate) fn footprint<'a>(
+        &self,
+        width: u32,
+        height: u32,
+        data: &'a [u8],
+    ) -> impl Iterator<Item = (i64, i64, [u8; 4])> + 'a {
+        let (w, h) = (width as i64, height as i64);
+        // Stamp pixel (w / 2, h / 2) lands on the anchor, as it always has,
+        // and the transform pivots on its centre
+        let (cx, cy) = ((w / 2) as f64 + 0.5, (h / 2) as f64 + 0.5);
+        let (sin, cos) = (self.rotation as f64).to_radians().sin_cos();
+        let scale = self.scale as f64;
+        let valid = scale > 0.0 && scale.is_finite() && self.rotation.is_finite() && data.len() >= (w * h * 4) as usize;
+
+        // Bounding box of the rotated, scaled corners
+        let corners = [(-cx, -cy), (w as f64 - cx, -cy), (-cx, h as f64 - cy), (w as f64 - cx, h as f64 - cy)];
+        let mut bounds = (0i64, 0i64, -1i64, -1i64);
+        if valid && w > 0 && h > 0 {
+            let (mut x0, mut y0, mut x1, mut y1) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+            for (u, v) in corners {
+                let (x, y) = ((u * cos - v * sin) * scale, (u * sin + v * cos) * scale);
+                (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+            }
+            bounds = (x0.floor() as i64, y0.floor() as i64, x1.ceil() as i64, y1.ceil() as i64);
+        }
+        let (flip_x, flip_y) = (self.flip_x, self.flip_y);
+
+        let (x0, y0, x1, y1) = bounds;
+        (y0..=y1).flat_map(move |dy| (x0..=x1).map(move |dx| (dx, dy))).filter_map(move |(dx, dy)| {
+            // Invert the transform at the cell's centre
+            let (px, py) = (dx as f64 / scale, dy as f64 / scale);
+            let u = (px * cos + py * sin + cx).floor() as i64;
+            let v = (-px * sin + py * cos + cy).floor() as i64;
+            if u < 0 || u >= w || v < 0 || v >= h {
+                return None;
+            }
+            let u = if flip_x { w - 1 - u } else { u };
+            let v = if flip_y { h - 1 - v } else { v };
+            let i = ((v * w + u) * 4) as usize;
+            let pixel = [data[i], data[i + 1], data[i + 2], data[i + 3]];
+            (pixel[3] > 0).then_some((dx, dy, pixel))
+        })
+    }
+}
+
+/// `old` with a stamp pixel blended in.
+pub(crate) fn blend(old: Individual, pixel: [u8; 4], mode: BlendMode, channel: LifeChannel) -> Individual {
+    let (h, s, l) = rgb_to_hsl(pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0);
+    let byte = |v: f32| (v * 255.0).round() as u8;
+    let stamp = Individual { hue: byte(h), saturation: byte(s), luminance: byte(l), alpha: 255 };
+    let target = match mode {
+        BlendMode::Replace => return Individual { alpha: pixel[3], ..stamp },
+        BlendMode::Lerp => stamp,
+        BlendMode::Add => Individual {
+            hue: old.hue.wrapping_add(stamp.hue),
+            saturation: old.saturation.saturating_add(stamp.saturation),
+            luminance: old.luminance.saturating_add(stamp.luminance),
+            alpha: 255,
+        },
+        BlendMode::Multiply => Individual {
+            saturation: (old.saturation as u16 * stamp.saturation as u16 / 255) as u8,
+            luminance: (old.luminance as u16 * stamp.luminance as u16 / 255) as u8,
+            ..old
+        },
+        BlendMode::Hue => Individual { hue: stamp.hue, ..old },
+        BlendMode::Luminance => Individual { luminance: stamp.luminance, ..old },
+        BlendMode::LifeChannel => match channel {
+            LifeChannel::Hue => Individual { hue: stamp.hue, ..old },
+            LifeChannel::Saturation => Individual { saturation: stamp.saturation, ..old },
+            LifeChannel::Luminance => Individual { luminance: stamp.luminance, ..old },
+            LifeChannel::Alpha => Individual { alpha: stamp.luminance, ..old },
+        },
+        BlendMode::Max => Individual {
+            hue: old.hue.max(stamp.hue),
+            saturation: old.saturation.max(stamp.saturation),
+            luminance: old.luminance.max(stamp.luminance),
+            alpha: 255,
+        },
+    };
+    if pixel[3] == 255 {
+        return target;
+    }
+    let t = pixel[3] as f32 / 255.0;
+    let lerp = |a: u8, b: u8| (a as f32 * (1.0 - t) + b as f32 * t).round() as u8;
+    Individual {
+        hue: lerp(old.hue, target.hue),
+        saturation: lerp(old.saturation, target.saturation),
+        luminance: lerp(old.luminance, target.luminance),
+        alpha: if mode == BlendMode::Lerp { 255 } else { lerp(old.alpha, target.alpha) },
+    }
+}
@@ -1,8 +1,12 @@
 #[cfg(test)]
 mod tests {
     use crate::{
-        LifeParams, LifeChannel, Individual, Universe, BrushState,
-        rgb_to_hsl, median_from_histogram
+        hsl_to_rgb, LifeParams, LifeChannel, Individual, Universe, BrushState, BitGrid, HashLife, StatsMode,
+        rgb_to_hsl, median_from_histogram, parse_rule, format_rule, parse_rle, write_rle, Pattern,
+        PatternFormat, detect_format, parse_pattern, write_pattern, parse_plaintext, parse_life105,
+        parse_life106, parse_macrocell, write_macrocell, preset, PRESETS, Palette, PALETTE_NAMES,
+        RenderSource, TrailMode, RgbaImage, AnimationFormat, AnimationOptions, Resample, ImportOptions,
+        Placement, Dither, BlendMode, StampOptions, BrushSettings, BrushShape
     };
 
     #[test]
@@ -290,15 +294,14 @@ mod tests {
 
         // Save state
         let state = universe.save_state();
-        assert_eq!(state.len(), universe.cells_len());
+        assert!(state.starts_with(b"RCA\x1a"));
 
         // Clear and verify it's cleared
         universe.clear();
         assert_eq!(universe.cells[0].hue, 0);
 
         // Load state and verify it's restored
-        let success = universe.load_state(&state);
-        assert!(success);
+        universe.load_state(&state).unwrap();
         assert_eq!(universe.cells[0].hue, 100);
         assert_eq!(universe.cells[0].saturation, 150);
         assert_eq!(universe.cells[0].luminance, 200);
@@ -306,8 +309,8 @@ mod tests {
 
         // Try loading wrong size state
         let wrong_size = vec![0; 10];
-        let success = universe.load_state(&wrong_size);
-        assert!(!success);
+        let err = universe.load_state(&wrong_size).unwrap_err();
+        assert!(err.contains("10x20"), "{}", err);
     }
 
     #[test]
@@ -695,4 +698,1568 @@ mod tests {
         universe.tick();
         assert!(true, "Tick should complete without crashing");
     }
-}
\ No newline at end of file
+
+    /// Straightforward per-cell reference used to validate the packed engines.
+    fn naive_step(alive: &[bool], width: usize, height: usize, rule: u32) -> Vec<bool> {
+        let mut next = vec![false; alive.len()];
+        for row in 0..height {
+            for col in 0..width {
+                let mut n = 0;
+                for dy in [height - 1, 0, 1] {
+                    for dx in [width - 1, 0, 1] {
+                        if dy == 0 && dx == 0 { continue; }
+                        if alive[((row + dy) % height) * width + (col + dx) % width] { n += 1; }
+                    }
+                }
+                let bit = n + if alive[row * width + col] { 9 } else { 0 };
+                next[row * width + col] = (rule >> bit) & 1 == 1;
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn test_bitgrid_matches_naive_step() {
+        // Conway B3/S23 and HighLife B36/S23 on widths around the word boundary
+        for &rule in &[0x1808u32, 0x1848] {
+            for &(width, height) in &[(3usize, 3usize), (64, 5), (70, 9), (130, 4)] {
+                let mut grid = BitGrid::new(width as u32, height as u32, rule);
+                let mut alive = vec![false; width * height];
+                let mut state = 0x2545F491u32;
+                for (i, cell) in alive.iter_mut().enumerate() {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    *cell = state.is_multiple_of(3);
+                    grid.set((i / width) as u32, (i % width) as u32, *cell);
+                }
+
+                for _ in 0..8 {
+                    alive = naive_step(&alive, width, height, rule);
+                    grid.step();
+                    for (i, &expected) in alive.iter().enumerate() {
+                        assert_eq!(grid.get((i / width) as u32, (i % width) as u32), expected,
+                            "mismatch at {} in {}x{}", i, width, height);
+                    }
+                }
+                assert_eq!(grid.population(), alive.iter().filter(|&&a| a).count());
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitgrid_cells_round_trip() {
+        let mut cells = vec![Individual::default(); 10 * 10];
+        let survivor = Individual { hue: 10, saturation: 20, luminance: 30, alpha: 255 };
+        // Blinker
+        cells[4 * 10 + 3] = survivor;
+        cells[4 * 10 + 4] = survivor;
+        cells[4 * 10 + 5] = survivor;
+
+        let mut grid = BitGrid::from_cells(&cells, 10, 10, 0x1808, LifeChannel::Alpha);
+        assert_eq!(grid.population(), 3);
+        grid.step();
+
+        let born = Individual { hue: 0, saturation: 0, luminance: 255, alpha: 255 };
+        grid.store_cells(&mut cells, LifeChannel::Alpha, born);
+        assert_eq!(cells[4 * 10 + 4], survivor);
+        assert_eq!(cells[3 * 10 + 4], born);
+        assert_eq!(cells[5 * 10 + 4], born);
+        assert_eq!(cells[4 * 10 + 3], Individual::default());
+        assert_eq!(cells[4 * 10 + 5], Individual::default());
+    }
+
+    #[test]
+    fn test_universe_binary_mode() {
+        let mut universe = Universe::new(10, 10);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        universe.set_binary_mode(true);
+        assert!(universe.binary_mode());
+
+        // Block plus a lone cell that should starve
+        universe.set_cell(4, 4, 100, 200, 200, 255);
+        universe.set_cell(4, 5, 100, 200, 200, 255);
+        universe.set_cell(5, 4, 100, 200, 200, 255);
+        universe.set_cell(5, 5, 100, 200, 200, 255);
+        universe.set_cell(0, 0, 100, 200, 200, 255);
+
+        // Drawn cells are carried over on the first tick
+        universe.tick();
+        assert_eq!(universe.stats().alive_count(), 5);
+
+        universe.tick();
+        let cells = universe.cells();
+        assert_eq!(cells[universe.index(4, 4)].hue, 100, "survivors keep their colour");
+        assert_eq!(cells[universe.index(0, 0)], Individual::default());
+        assert_eq!(universe.stats().alive_count(), 4);
+
+        universe.resize(20, 20);
+        assert!(universe.binary_mode());
+        universe.set_binary_mode(false);
+        assert!(!universe.binary_mode());
+    }
+
+    #[test]
+    fn test_binary_mode_syncs_cells_on_demand() {
+        let binary = |seed| {
+            let mut universe = Universe::new(70, 40);
+            universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+            universe.set_binary_mode(true);
+            universe.set_seed(seed);
+            universe.randomize();
+            universe.set_stats_mode(StatsMode::Full);
+            universe
+        };
+
+        // A run synced once at the end matches one synced every generation
+        let (mut stepped, mut run) = (binary(3), binary(3));
+        for _ in 0..12 {
+            stepped.tick();
+        }
+        run.tick_n(12);
+        assert_eq!(stepped.cells(), run.cells());
+
+        // Edits between ticks reach the resident grid
+        for universe in [&mut stepped, &mut run] {
+            universe.toggle(10, 10);
+            universe.set_cell(20, 65, 40, 200, 200, 255);
+            universe.tick();
+        }
+        assert_eq!(stepped.cells(), run.cells());
+
+        // Every statistic matches a full pass over the cells
+        let cells = run.cells();
+        let alive = cells.iter().filter(|c| c.activity_value(LifeChannel::Luminance) > 0).count();
+        let sum = |f: fn(&Individual) -> u8| cells.iter().map(|c| f(c) as f32).sum::<f32>() / cells.len() as f32;
+        let stats = run.stats();
+        assert_eq!(stats.alive_count(), alive);
+        assert_eq!(stats.dead_count(), cells.len() - alive);
+        assert!((stats.avg_hue() - sum(|c| c.hue)).abs() < 1e-3);
+        assert!((stats.avg_luminance() - sum(|c| c.luminance)).abs() < 1e-3);
+        assert_eq!(stats.median_hue(), stepped.stats().median_hue());
+    }
+
+    #[test]
+    fn test_hashlife_matches_naive_step() {
+        let (width, height) = (64usize, 64usize);
+        let mut alive = vec![false; width * height];
+        let mut life = HashLife::new(0x1808).unwrap();
+        let mut state = 0x9E3779B9u32;
+        for row in 26..38 {
+            for col in 26..38 {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                if state & 1 == 1 {
+                    alive[row * width + col] = true;
+                    life.set_cell(col as i64, row as i64, true);
+                }
+            }
+        }
+
+        // Small caps on the step size and node store exercise both code paths
+        life.set_max_step_log2(2);
+        life.set_max_nodes(0);
+        for generations in [1u64, 2, 3, 7] {
+            for _ in 0..generations {
+                alive = naive_step(&alive, width, height, 0x1808);
+            }
+            life.advance(generations);
+            for (i, &expected) in alive.iter().enumerate() {
+                assert_eq!(life.get_cell((i % width) as i64, (i / width) as i64), expected);
+            }
+        }
+        assert_eq!(life.generation(), 13);
+        assert_eq!(life.population() as usize, alive.iter().filter(|&&a| a).count());
+    }
+
+    #[test]
+    fn test_hashlife_glider_far_future() {
+        let mut life = HashLife::new(0x1808).unwrap();
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            life.set_cell(x, y, true);
+        }
+
+        // A glider moves one cell diagonally every four generations
+        life.advance(1_000_000);
+        assert_eq!(life.generation(), 1_000_000);
+        assert_eq!(life.population(), 5);
+        let mut cells = life.live_cells();
+        cells.sort();
+        let mut expected: Vec<(i64, i64)> = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+            .iter()
+            .map(|&(x, y)| (x + 250_000, y + 250_000))
+            .collect();
+        expected.sort();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn test_hashlife_extreme_coordinates() {
+        let mut life = HashLife::new(0x1808).unwrap();
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            life.set_cell(x, y, true);
+        }
+        life.set_max_step_log2(255);
+        life.advance(u64::MAX >> 1);
+        assert_eq!(life.generation(), u64::MAX >> 1);
+        assert_eq!(life.population(), 4);
+        assert!(life.get_cell(1, 1));
+
+        // Cells beyond the plane's span are out of reach, not overflows
+        life.set_cell(i64::MAX, i64::MIN, true);
+        assert_eq!(life.population(), 4);
+        assert!(!life.get_cell(i64::MAX, i64::MIN));
+
+        let universe = Universe::new(8, 8);
+        life.import_region(&universe, u32::MAX - 1, u32::MAX, u32::MAX, 10);
+        assert_eq!(life.population(), 0);
+    }
+
+    #[test]
+    fn test_hashlife_rejects_b0() {
+        assert!(HashLife::new(0x1809).is_err());
+    }
+
+    #[test]
+    fn test_hashlife_universe_region() {
+        let mut universe = Universe::new(20, 20);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        // Blinker inside the region starting at (5, 5)
+        universe.set_cell(7, 6, 10, 20, 30, 255);
+        universe.set_cell(7, 7, 10, 20, 30, 255);
+        universe.set_cell(7, 8, 10, 20, 30, 255);
+
+        let mut life = HashLife::new(universe.params().rule).unwrap();
+        life.import_region(&universe, 5, 5, 10, 10);
+        assert_eq!(life.population(), 3);
+        assert!(life.get_cell(2, 2));
+
+        life.advance(1001);
+        life.export_region(&mut universe, 5, 5, 10, 10);
+        let cells = universe.cells();
+        assert_eq!(cells[universe.index(7, 7)].hue, 10, "survivor keeps its colour");
+        assert_eq!(cells[universe.index(6, 7)].alpha, 255);
+        assert_eq!(cells[universe.index(8, 7)].alpha, 255);
+        assert_eq!(cells[universe.index(7, 6)], Individual::default());
+    }
+
+    #[test]
+    fn test_hashlife_export_into_binary_universe() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+        universe.set_binary_mode(true);
+        universe.set_history_capacity(8);
+        universe.set_cell(0, 0, 0, 0, 255, 255);
+
+        // A vertical blinker, exported like a load
+        let mut life = HashLife::new(0x1808).unwrap();
+        for row in 0..3 {
+            life.set_cell(1, row, true);
+        }
+        life.export_region(&mut universe, 4, 4, 3, 3);
+        assert!(!universe.can_undo());
+        assert!(!universe.step_back());
+
+        let alive = |u: &Universe| -> Vec<(u32, u32)> {
+            (0..16 * 16).filter(|&i| u.cells()[i as usize].luminance > 0).map(|i| (i / 16, i % 16)).collect()
+        };
+        assert_eq!(alive(&universe), vec![(0, 0), (4, 5), (5, 5), (6, 5)]);
+        universe.tick();
+        assert_eq!(alive(&universe), vec![(5, 4), (5, 5), (5, 6)]);
+        universe.tick();
+        assert_eq!(alive(&universe), vec![(4, 5), (5, 5), (6, 5)]);
+    }
+
+    #[test]
+    fn test_universe_tick_tiles_match_full_recompute() {
+        let seed = |universe: &mut Universe| {
+            universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.0, 0.1, LifeChannel::Alpha);
+            // Glider heading across tile borders plus a block
+            for (row, col) in [(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)] {
+                universe.set_cell(row + 28, col + 28, 40, 200, 200, 255);
+            }
+            for (row, col) in [(60, 90), (60, 91), (61, 90), (61, 91)] {
+                universe.set_cell(row, col, 180, 100, 150, 255);
+            }
+        };
+
+        let mut tiled = Universe::new(100, 70);
+        let mut full = Universe::new(100, 70);
+        seed(&mut tiled);
+        seed(&mut full);
+
+        for _ in 0..120 {
+            full.tiles.mark_all();
+            full.tick();
+            tiled.tick();
+            assert_eq!(tiled.cells(), full.cells());
+            assert_eq!(tiled.stats().alive_count(), full.stats().alive_count());
+            assert_eq!(tiled.stats().avg_hue(), full.stats().avg_hue());
+            assert_eq!(tiled.stats().median_saturation(), full.stats().median_saturation());
+        }
+    }
+
+    #[test]
+    fn test_universe_tick_settles_to_no_active_tiles() {
+        let mut universe = Universe::new(64, 64);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        universe.set_cell(10, 10, 100, 100, 100, 255);
+        universe.set_cell(10, 11, 100, 100, 100, 255);
+        universe.set_cell(11, 10, 100, 100, 100, 255);
+        universe.set_cell(11, 11, 100, 100, 100, 255);
+        assert_eq!(universe.active_tiles(), universe.tile_count());
+
+        for _ in 0..10 {
+            universe.tick();
+        }
+        assert_eq!(universe.active_tiles(), 0, "a saturated block should leave nothing to recompute");
+        assert_eq!(universe.stats().alive_count(), 4);
+
+        // Drawing wakes the tile and its neighbours
+        universe.set_cell(40, 40, 100, 100, 100, 255);
+        assert_eq!(universe.active_tiles(), 4);
+        universe.tick();
+        universe.tick();
+        let lone = universe.cells()[universe.index(40, 40)];
+        assert!(lone.alpha < 255, "the lone cell should start dying once released");
+    }
+
+    #[test]
+    fn test_universe_seed_is_reproducible() {
+        let run = |seed: u64| {
+            let mut universe = Universe::new(40, 30);
+            universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.5, 0.1, LifeChannel::Alpha);
+            universe.set_seed(seed);
+            universe.randomize();
+            for _ in 0..10 {
+                universe.tick();
+            }
+            universe.cells().clone()
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+
+        let mut universe = Universe::new(4, 4);
+        universe.set_seed(7);
+        assert_eq!(universe.seed(), 7);
+    }
+
+    #[test]
+    fn test_universe_load_state_resumes_rng() {
+        let mut universe = Universe::new(40, 30);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.5, 0.1, LifeChannel::Alpha);
+        universe.set_seed(99);
+        universe.randomize();
+        universe.tick();
+        let saved = universe.save_state();
+
+        for _ in 0..5 {
+            universe.tick();
+        }
+        let expected = universe.cells().clone();
+
+        let mut restored = Universe::new(40, 30);
+        restored.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.5, 0.1, LifeChannel::Alpha);
+        restored.load_state(&saved).unwrap();
+        assert_eq!(restored.seed(), 99);
+        for _ in 0..5 {
+            restored.tick();
+        }
+        assert_eq!(restored.cells(), &expected);
+
+        // Legacy raw saves, with and without the RNG trailer, still load
+        let mut legacy: Vec<u8> = expected
+            .iter()
+            .flat_map(|c| [c.hue, c.saturation, c.luminance, c.alpha])
+            .collect();
+        restored.load_state(&legacy).unwrap();
+        assert_eq!(restored.cells(), &expected);
+        legacy.extend_from_slice(&5u64.to_le_bytes());
+        legacy.extend_from_slice(&6u64.to_le_bytes());
+        restored.load_state(&legacy).unwrap();
+        assert_eq!(restored.seed(), 6);
+    }
+
+    #[test]
+    fn test_universe_tick_n_matches_ticks() {
+        let make = || {
+            let mut universe = Universe::new(50, 40);
+            universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.3, 0.1, LifeChannel::Alpha);
+            universe.set_seed(5);
+            universe.randomize();
+            universe
+        };
+
+        let mut single = make();
+        for _ in 0..7 {
+            single.tick();
+        }
+        let mut batch = make();
+        batch.tick_n(7);
+
+        assert_eq!(batch.generation(), 7);
+        assert_eq!(batch.cells(), single.cells());
+        let (a, b) = (batch.stats(), single.stats());
+        assert_eq!(a.alive_count(), b.alive_count());
+        assert_eq!(a.avg_luminance(), b.avg_luminance());
+        assert_eq!(a.median_hue(), b.median_hue());
+        assert_eq!(a.median_alpha(), b.median_alpha());
+    }
+
+    #[test]
+    fn test_universe_stats_modes() {
+        let mut universe = Universe::new(40, 40);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        universe.set_seed(1);
+        universe.randomize();
+
+        universe.set_stats_mode(StatsMode::Off);
+        universe.tick_n(3);
+        assert_eq!(universe.stats().alive_count(), 0, "no stats gathered while off");
+
+        universe.set_stats_mode(StatsMode::Basic);
+        universe.tick();
+        let alive = universe.cells().iter().filter(|c| c.alpha > 0).count();
+        assert_eq!(universe.stats().alive_count(), alive);
+        assert_eq!(universe.stats().median_alpha(), 0.0, "basic stats skip medians");
+
+        universe.set_stats_mode(StatsMode::EveryN);
+        universe.set_stats_interval(3);
+        // Generation 5 is skipped, 6 is gathered
+        universe.tick();
+        assert_eq!(universe.stats().alive_count(), alive);
+        universe.tick();
+        let alive = universe.cells().iter().filter(|c| c.alpha > 0).count();
+        assert_eq!(universe.generation(), 6);
+        assert_eq!(universe.stats().alive_count(), alive);
+
+        // Stale tiles are rescanned so full stats after skipped ones are exact
+        let mut full = Universe::new(40, 40);
+        full.load_state(&universe.save_state()).unwrap();
+        full.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        universe.set_stats_mode(StatsMode::Full);
+        universe.tick();
+        full.tick();
+        assert_eq!(universe.cells(), full.cells());
+        assert_eq!(universe.stats().avg_hue(), full.stats().avg_hue());
+        assert_eq!(universe.stats().median_saturation(), full.stats().median_saturation());
+    }
+
+    #[test]
+    fn test_parse_rule_notations() {
+        assert_eq!(parse_rule("B3/S23"), Ok(0x1808));
+        assert_eq!(parse_rule("b3s23"), Ok(0x1808));
+        assert_eq!(parse_rule("S23/B3"), Ok(0x1808));
+        assert_eq!(parse_rule("23/3"), Ok(0x1808));
+        assert_eq!(parse_rule("B36/S23"), Ok(0x1848));
+        assert_eq!(parse_rule("B2/S"), Ok(0x4));
+        assert!(parse_rule("B9/S23").is_err());
+        assert!(parse_rule("B3/B6").is_err());
+        assert!(parse_rule("Life").is_err());
+    }
+
+    #[test]
+    fn test_format_rule_round_trip() {
+        assert_eq!(format_rule(0x1808), "B3/S23");
+        assert_eq!(format_rule(0), "B/S");
+        let default_rule = LifeParams::default().rule;
+        assert_eq!(parse_rule(&format_rule(default_rule)), Ok(default_rule));
+    }
+
+    #[test]
+    fn test_history_step_back_and_replay() {
+        let mut universe = Universe::new(48, 48);
+        universe.set_seed(11);
+        universe.randomize();
+        universe.set_history_capacity(8);
+        let start = universe.cells().clone();
+
+        for _ in 0..5 {
+            universe.tick();
+        }
+        let end = universe.cells().clone();
+        assert_eq!(universe.history_start(), 0);
+
+        assert!(universe.step_back());
+        assert!(universe.step_back());
+        assert_eq!(universe.generation(), 3);
+        assert_eq!(universe.history_end(), 5);
+
+        assert!(universe.seek(0));
+        assert_eq!(universe.cells(), &start);
+        assert!(!universe.step_back());
+
+        // Replaying and recomputing both reproduce the original run
+        assert!(universe.seek(5));
+        assert_eq!(universe.cells(), &end);
+        universe.seek(2);
+        universe.tick_n(3);
+        assert_eq!(universe.generation(), 5);
+        assert_eq!(universe.cells(), &end);
+        assert_eq!(universe.history_end(), 5);
+    }
+
+    #[test]
+    fn test_history_includes_brush_edits() {
+        let mut universe = Universe::new(32, 32);
+        universe.set_history_capacity(4);
+        universe.tick();
+        let before = universe.cells().clone();
+
+        universe.set_cell(5, 5, 10, 200, 200, 255);
+        let edited = universe.cells().clone();
+        universe.tick();
+        universe.tick();
+        let end = universe.cells().clone();
+
+        // Stepping back lands on the edited grid, then before the edit
+        universe.step_back();
+        universe.step_back();
+        assert_eq!(universe.cells(), &edited);
+        universe.step_back();
+        assert_eq!(universe.cells(), &before);
+
+        universe.seek(3);
+        assert_eq!(universe.cells(), &end);
+    }
+
+    #[test]
+    fn test_history_reverts_repeated_edits_and_toggles() {
+        let mut universe = Universe::new(32, 32);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+        universe.set_binary_mode(true);
+        universe.set_history_capacity(4);
+        universe.tick();
+        let before = universe.cells().clone();
+
+        // A cell brushed twice returns to its original value, whether the
+        // edits are pending or were ticked
+        universe.draw_brush(5, 5, 0, true, 10, 200, 200, 1);
+        universe.draw_brush(5, 5, 0, true, 90, 100, 100, 2);
+        universe.tick();
+        universe.draw_brush(9, 9, 0, true, 10, 200, 200, 3);
+        universe.draw_brush(9, 9, 0, true, 90, 100, 100, 4);
+        assert!(universe.step_back());
+        assert!(universe.step_back());
+        assert_eq!(universe.cells(), &before);
+
+        // Toggles are recorded too, and replay without being held
+        universe.seek(1);
+        for col in 15..18 {
+            universe.toggle(8, col);
+        }
+        let toggled = universe.cells().clone();
+        universe.tick();
+        let ticked = universe.cells().clone();
+        assert!(universe.step_back());
+        assert_eq!(universe.cells(), &toggled);
+        universe.tick();
+        assert_eq!(universe.cells(), &ticked);
+        assert!(universe.step_back());
+        assert!(universe.step_back());
+        assert_eq!(universe.cells(), &before);
+        assert!(universe.step_forward());
+        assert!(universe.step_forward());
+        assert_eq!(universe.cells(), &ticked);
+    }
+
+    #[test]
+    fn test_history_capacity_limits_rewind() {
+        let mut universe = Universe::new(32, 32);
+        universe.set_seed(3);
+        universe.randomize();
+        universe.set_history_capacity(3);
+        universe.tick_n(10);
+        assert_eq!(universe.history_start(), 7);
+        assert!(!universe.seek(6));
+        assert!(universe.seek(7));
+        assert_eq!(universe.generation(), 7);
+
+        universe.set_history_capacity(0);
+        assert!(!universe.step_back());
+        assert!(universe.seek(8));
+        assert_eq!(universe.generation(), 8);
+    }
+
+    #[test]
+    fn test_undo_redo_strokes_and_stamps() {
+        let mut universe = Universe::new(32, 32);
+        let blank = universe.cells().clone();
+
+        // Dabs sharing a brush id form one stroke
+        universe.draw_brush(8, 8, 1, true, 10, 200, 200, 1);
+        universe.draw_brush(9, 8, 1, true, 10, 200, 200, 1);
+        let stroke = universe.cells().clone();
+        let stamp = [255u8, 0, 0, 255].repeat(4);
+        universe.draw_stamp_at(20, 20, 2, 2, &stamp);
+        let stamped = universe.cells().clone();
+
+        assert!(universe.undo());
+        assert_eq!(universe.cells(), &stroke);
+        assert!(universe.undo());
+        assert_eq!(universe.cells(), &blank);
+        assert!(!universe.undo());
+
+        assert!(universe.redo());
+        assert!(universe.redo());
+        assert_eq!(universe.cells(), &stamped);
+        assert!(!universe.redo());
+
+        // A new edit discards what could be redone
+        universe.undo();
+        universe.draw_brush(2, 2, 0, true, 10, 200, 200, 2);
+        assert!(!universe.can_redo());
+    }
+
+    #[test]
+    fn test_replacing_the_grid_clears_undo() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_seed(2);
+        universe.randomize();
+        let saved = universe.save_state();
+        let compressed = universe.save_state_compressed();
+
+        universe.draw_brush(4, 4, 1, true, 10, 200, 200, 1);
+        universe.load_state(&saved).unwrap();
+        let loaded = universe.cells().clone();
+        assert!(!universe.undo());
+        assert_eq!(universe.cells(), &loaded);
+
+        universe.draw_brush(4, 4, 1, true, 10, 200, 200, 2);
+        universe.load_state_compressed(&compressed).unwrap();
+        assert!(!universe.undo());
+        assert_eq!(universe.cells(), &loaded);
+
+        universe.draw_brush(4, 4, 1, true, 10, 200, 200, 3);
+        universe.randomize();
+        assert!(!universe.can_undo());
+        universe.draw_brush(4, 4, 1, true, 10, 200, 200, 4);
+        universe.clear();
+        assert!(!universe.can_undo());
+        universe.draw_brush(4, 4, 1, true, 10, 200, 200, 5);
+        universe.set_grid(0, 0, 255, 255);
+        assert!(!universe.can_undo());
+    }
+
+    #[test]
+    fn test_undo_limit_drops_oldest_edits() {
+        let mut universe = Universe::new(32, 32);
+        universe.set_undo_limit(1);
+        universe.draw_brush(5, 5, 2, true, 10, 200, 200, 1);
+        universe.draw_brush(20, 20, 2, true, 10, 200, 200, 2);
+        assert!(universe.undo());
+        assert!(!universe.undo());
+
+        universe.set_undo_limit(0);
+        universe.draw_brush(5, 5, 2, true, 10, 200, 200, 3);
+        assert!(!universe.can_undo());
+    }
+
+    #[test]
+    fn test_cycle_detection_period_and_start() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+        universe.set_binary_mode(true);
+        universe.set_cycle_detection(true);
+        // A blinker; toggled cells are not held like drawn ones
+        for col in 6..9 {
+            universe.toggle(8, col);
+        }
+        universe.tick_n(6);
+        assert_eq!(universe.detected_period(), Some(2));
+        assert_eq!(universe.cycle_start(), Some(1));
+        assert!(!universe.is_extinct());
+
+        // Edits void what was seen
+        universe.set_cell(0, 0, 0, 0, 255, 255);
+        universe.tick();
+        assert_eq!(universe.detected_period(), None);
+    }
+
+    #[test]
+    fn test_cycle_detection_still_life_and_extinction() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        universe.set_cycle_detection(true);
+        universe.set_stagnation_window(5);
+        for (row, col) in [(4, 4), (4, 5), (5, 4), (5, 5)] {
+            universe.set_cell(row, col, 0, 100, 200, 255);
+        }
+        universe.tick_n(20);
+        assert_eq!(universe.detected_period(), Some(1));
+        assert!(universe.is_stagnant());
+
+        let mut lonely = Universe::new(16, 16);
+        lonely.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        lonely.set_binary_mode(true);
+        lonely.set_cycle_detection(true);
+        lonely.set_cell(3, 3, 0, 0, 255, 255);
+        lonely.tick_n(2);
+        assert!(lonely.is_extinct());
+    }
+
+    #[test]
+    fn test_cycle_detection_follows_changed_tiles() {
+        // A glider crosses every tile and only returns to its exact state
+        // after 4 * 64 generations, so every tile's hash must keep up
+        let mut universe = Universe::new(64, 64);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+        universe.set_binary_mode(true);
+        universe.set_cycle_detection(true);
+        for (row, col) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            universe.toggle(row, col);
+        }
+        universe.tick_n(200);
+        assert_eq!(universe.detected_period(), None);
+        universe.tick_n(57);
+        assert_eq!(universe.detected_period(), Some(256));
+        assert_eq!(universe.cycle_start(), Some(1));
+    }
+
+    #[test]
+    fn test_parse_rle_glider() {
+        let text = "#N Glider\n#C A comment\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+        let pattern = parse_rle(text).unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.rule, Some(0x1808));
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+        // Multi-state letters, blank rows and unsupported rules
+        let multi = parse_rle("x = 4, y = 3, rule = B2/S/C3\n.A2.pB$2$3B!").unwrap();
+        assert_eq!(multi.rule, None);
+        assert_eq!(multi.cells, vec![(1, 0), (4, 0), (0, 3), (1, 3), (2, 3)]);
+
+        assert!(parse_rle("x = 3, y = 3\nbo?!").is_err());
+        assert!(parse_rle("x = three, y = 3\n!").is_err());
+        assert!(parse_rle("4294967295o!").is_err());
+        assert!(parse_rle("2b4294967295b!").is_err());
+        assert!(parse_rle("4294967295$2$o!").is_err());
+        assert!(parse_rle("100000000o!").is_err());
+    }
+
+    #[test]
+    fn test_write_rle_round_trip() {
+        let pattern = Pattern {
+            width: 80,
+            height: 4,
+            cells: (0..80).step_by(2).map(|x| (x, 0)).chain([(5, 3), (6, 3)]).collect(),
+            rule: Some(0x1848),
+        };
+        let text = write_rle(&pattern);
+        assert!(text.starts_with("x = 80, y = 4, rule = B36/S23\n"));
+        assert!(text.lines().all(|line| line.len() <= 70));
+        assert!(text.contains("3$5b2o!"));
+        assert_eq!(parse_rle(&text).unwrap(), pattern);
+    }
+
+    #[test]
+    fn test_universe_load_and_export_rle() {
+        let mut universe = Universe::new(20, 20);
+        universe.set_pattern_color(10, 20, 200, 255);
+        universe.load_rle("x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!", 18, 5).unwrap();
+        assert_eq!(universe.params().rule, 0x1848);
+
+        // Placement wraps around the right edge
+        let idx = universe.index(7, 0);
+        assert_eq!(universe.cells()[idx], Individual { hue: 10, saturation: 20, luminance: 200, alpha: 255 });
+        assert_eq!(universe.export_rle(18, 5, 3, 3), "x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!\n");
+
+        assert!(universe.undo());
+        assert_eq!(universe.export_rle(0, 0, 20, 20), "x = 20, y = 20, rule = B36/S23\n!\n");
+        assert!(universe.load_rle("x = 1, y = 1\n3q!", 0, 0).is_err());
+    }
+
+    fn glider() -> Pattern {
+        Pattern { width: 3, height: 3, cells: vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)], rule: Some(0x1808) }
+    }
+
+    #[test]
+    fn test_pattern_format_readers() {
+        let cells = parse_plaintext("!Name: Glider\n.O\n..O\nOOO\n\n").unwrap();
+        assert_eq!(cells, Pattern { rule: None, ..glider() });
+
+        let life106 = parse_life106("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(life106, Pattern { rule: None, ..glider() });
+
+        let life105 = parse_life105("#Life 1.05\n#D Glider\n#R 23/36\n#P -1 -1\n.*\n#P -1 0\n..*\n***\n").unwrap();
+        assert_eq!(life105, Pattern { rule: Some(0x1848), ..glider() });
+
+        // Two copies of the same leaf under one level-4 node
+        let mc = parse_macrocell("[M2] (golly 4.2)\n#R B3/S23\n$.*$..*$***$\n4 1 0 0 1\n").unwrap();
+        assert_eq!((mc.width, mc.height, mc.cells.len()), (11, 11, 10));
+        assert_eq!(mc.cells[..5], glider().cells[..]);
+
+        assert!(parse_life106("#Life 1.06\n1 x\n").is_err());
+        assert_eq!(
+            parse_life106("#Life 1.06\n-9223372036854775808 0\n9223372036854775807 0\n"),
+            Err("pattern is too large".to_string())
+        );
+        assert!(parse_macrocell("[M2]\n4 1 0 0 7\n").is_err());
+        assert!(parse_macrocell("[M2]\n*$\n5 1 0 0 0\n").is_err());
+
+        // Each level sharing the one below four times: tiny file, 4^60 cells
+        let mut bomb = String::from("[M2]\n*$\n");
+        for (id, level) in (4..64).enumerate() {
+            let child = id + 1;
+            bomb.push_str(&format!("{} {} {} {} {}\n", level, child, child, child, child));
+        }
+        assert!(parse_macrocell(&bomb).is_err());
+        assert!(parse_plaintext(".O\n.X\n").is_err());
+    }
+
+    #[test]
+    fn test_pattern_format_round_trips() {
+        let formats = [
+            PatternFormat::Rle,
+            PatternFormat::Plaintext,
+            PatternFormat::Life105,
+            PatternFormat::Life106,
+            PatternFormat::Macrocell,
+        ];
+        for format in formats {
+            let text = write_pattern(&glider(), format);
+            assert_eq!(detect_format(&text), format);
+            let pattern = parse_pattern(&text).unwrap();
+            assert_eq!(pattern.cells, glider().cells, "{:?}", format);
+            let keeps_rule = !matches!(format, PatternFormat::Plaintext | PatternFormat::Life106);
+            assert_eq!(pattern.rule.is_some(), keeps_rule, "{:?}", format);
+        }
+
+        // Repeated subtrees are shared
+        let mut big = glider();
+        big.cells.extend(glider().cells.iter().map(|&(x, y)| (x + 64, y + 64)));
+        big.width = 67;
+        big.height = 67;
+        let text = write_macrocell(&big);
+        assert_eq!(text.lines().filter(|l| l.ends_with('$')).count(), 1);
+        assert_eq!(parse_macrocell(&text).unwrap().cells.len(), 10);
+    }
+
+    #[test]
+    fn test_universe_load_and_export_pattern() {
+        let mut universe = Universe::new(16, 16);
+        universe.load_pattern("#Life 1.06\n0 0\n1 0\n2 0\n", 4, 4).unwrap();
+        assert_eq!(universe.export_pattern(4, 4, 3, 1, PatternFormat::Plaintext), "OOO\n");
+        assert!(universe.load_pattern("#Life 1.06\nnope\n", 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_save_state_container_restores_everything() {
+        let mut universe = Universe::new(24, 12);
+        universe.set_params(0x1848, 2, 40, 0.7, 0.5, 0.9, 0.9, 0.8, 0.02, 0.2, LifeChannel::Luminance);
+        universe.set_seed(5);
+        universe.randomize();
+        universe.tick_n(3);
+        universe.set_cell(2, 2, 1, 2, 3, 4);
+        let saved = universe.save_state();
+
+        // Dimensions, params, generation and pending edits come from the save
+        let mut restored = Universe::new(4, 4);
+        restored.load_state(&saved).unwrap();
+        assert_eq!((restored.width(), restored.height()), (24, 12));
+        assert_eq!(restored.params().rule, 0x1848);
+        assert_eq!(restored.params().life_channel, LifeChannel::Luminance);
+        assert_eq!(restored.params().hue_lerp_factor, 0.2);
+        assert_eq!(restored.generation(), 3);
+        assert_eq!(restored.seed(), 5);
+        assert_eq!(restored.cells(), universe.cells());
+
+        universe.tick_n(4);
+        restored.tick_n(4);
+        assert_eq!(restored.cells(), universe.cells());
+    }
+
+    #[test]
+    fn test_load_state_rejects_damaged_containers() {
+        let mut universe = Universe::new(8, 8);
+        let saved = universe.save_state();
+
+        let mut corrupt = saved.clone();
+        corrupt[40] ^= 1;
+        assert!(universe.load_state(&corrupt).unwrap_err().contains("checksum"));
+        assert!(universe.load_state(&saved[..20]).is_err());
+
+        // Re-seal after editing the header so only it is wrong
+        let reseal = |mut bytes: Vec<u8>| {
+            let body = bytes.len() - 4;
+            let mut c = !0u32;
+            for &b in &bytes[..body] {
+                c ^= b as u32;
+                for _ in 0..8 {
+                    c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+                }
+            }
+            bytes[body..].copy_from_slice(&(!c).to_le_bytes());
+            bytes
+        };
+
+        let mut newer = saved.clone();
+        newer[4] = 9;
+        assert!(universe.load_state(&reseal(newer)).unwrap_err().contains("version 9"));
+
+        let mut unversioned = saved.clone();
+        unversioned[4] = 0;
+        assert!(universe.load_state(&reseal(unversioned)).unwrap_err().contains("version 0"));
+
+        // Dimensions whose cell count overflows
+        let mut huge = saved.clone();
+        huge[6..14].fill(0xFF);
+        assert!(universe.load_state(&reseal(huge)).unwrap_err().contains("too large"));
+        assert_eq!(universe.cells().len(), 64);
+    }
+
+    #[test]
+    fn test_compressed_state_round_trip() {
+        let mut universe = Universe::new(64, 48);
+        universe.set_seed(8);
+        universe.randomize();
+        universe.tick_n(2);
+        let packed = universe.save_state_compressed();
+        assert!(packed.len() < universe.save_state().len());
+
+        let mut restored = Universe::new(1, 1);
+        restored.load_state_compressed(&packed).unwrap();
+        assert_eq!(restored.cells(), universe.cells());
+        assert_eq!(restored.generation(), 2);
+
+        // A mostly dead HD grid shrinks by orders of magnitude
+        let mut sparse = Universe::new(1920, 1080);
+        sparse.set_cell(10, 10, 40, 200, 200, 255);
+        sparse.set_cell(500, 700, 40, 200, 200, 255);
+        let packed = sparse.save_state_compressed();
+        assert!(packed.len() < 1024, "{} bytes", packed.len());
+        restored.load_state_compressed(&packed).unwrap();
+        assert_eq!(restored.cells(), sparse.cells());
+    }
+
+    #[test]
+    fn test_load_state_compressed_rejects_bad_input() {
+        let mut universe = Universe::new(16, 16);
+        universe.randomize();
+        let packed = universe.save_state_compressed();
+
+        assert!(universe.load_state_compressed(&universe.save_state()).is_err());
+        assert!(universe.load_state_compressed(&packed[..packed.len() - 3]).is_err());
+        let mut corrupt = packed.clone();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0x55;
+        assert!(universe.load_state_compressed(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_life_params_json_round_trip() {
+        let params = LifeParams::new(0x1848, 2, 40, 0.7, 0.5, 0.9, 0.9, 0.8, 0.02, 0.2, LifeChannel::Luminance);
+        let json = params.to_json();
+        assert!(json.contains("\"life_channel\":\"luminance\""));
+        assert_eq!(LifeParams::from_json(&json), Ok(params));
+
+        // Notation rules, capitalised channels and missing fields
+        let partial = LifeParams::from_json(r#"{"rule": "B3/S23", "life_channel": "Hue"}"#).unwrap();
+        assert_eq!(partial.rule, 0x1808);
+        assert_eq!(partial.life_channel, LifeChannel::Hue);
+        assert_eq!(partial.decay_step, LifeParams::default().decay_step);
+
+        assert!(LifeParams::from_json(r#"{"rule": "B9/S"}"#).is_err());
+        assert!(LifeParams::from_json(r#"{"life_channel": "red"}"#).is_err());
+
+        let mut universe = Universe::new(8, 8);
+        universe.set_params_json(&json).unwrap();
+        assert_eq!(universe.params(), &params);
+        assert_eq!(universe.params_json(), json);
+    }
+
+    #[test]
+    fn test_presets_apply_by_name() {
+        let listed: serde_json::Value = serde_json::from_str(&Universe::presets_json()).unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), PRESETS.len());
+        assert_eq!(listed[0]["name"], PRESETS[0].name);
+
+        for p in PRESETS {
+            // Descriptions quoting a rule must match it
+            let rule = format_rule(p.params.rule);
+            if p.description.contains("/S") {
+                assert!(p.description.contains(&rule), "{}: {}", p.name, rule);
+            }
+        }
+
+        let mut universe = Universe::new(16, 16);
+        universe.apply_preset("HighLife").unwrap();
+        assert_eq!(universe.params(), &preset("highlife").unwrap().params);
+        assert_eq!(universe.seed(), preset("highlife").unwrap().seed);
+        assert!(universe.apply_preset("nope").is_err());
+    }
+
+    #[test]
+    fn test_hsl_to_rgb_inverts_rgb_to_hsl() {
+        let near = |(r, g, b): (f32, f32, f32), (r2, g2, b2): (f32, f32, f32)| {
+            (r - r2).abs() < 1e-5 && (g - g2).abs() < 1e-5 && (b - b2).abs() < 1e-5
+        };
+        assert!(near(hsl_to_rgb(0.0, 1.0, 0.5), (1.0, 0.0, 0.0)));
+        assert_eq!(hsl_to_rgb(0.5, 0.0, 0.25), (0.25, 0.25, 0.25));
+        for &(r, g, b) in &[(0.2f32, 0.4f32, 0.6f32), (0.9, 0.1, 0.3), (0.5, 0.5, 0.1), (1.0, 1.0, 1.0)] {
+            let (h, s, l) = rgb_to_hsl(r, g, b);
+            assert!(near(hsl_to_rgb(h, s, l), (r, g, b)), "{:?}", (r, g, b));
+        }
+
+        let cell = Individual { hue: 85, saturation: 255, luminance: 128, alpha: 7 };
+        assert_eq!(cell.to_rgba(true, false), [1, 255, 1, 7]);
+        assert_eq!(cell.to_rgba(false, false)[3], 255);
+        assert_eq!(cell.to_rgba(true, true), [85, 255, 128, 7]);
+    }
+
+    #[test]
+    fn test_rgba_framebuffer_follows_ticks() {
+        let mut universe = Universe::new(70, 40);
+        universe.set_seed(4);
+        universe.randomize();
+        assert_eq!(universe.rgba_len(), 0);
+
+        universe.set_rgba_enabled(true);
+        universe.set_display_mode(false, false);
+        let expected = |u: &Universe| -> Vec<u8> { u.cells().iter().flat_map(|c| c.to_rgba(false, false)).collect() };
+        for _ in 0..5 {
+            universe.tick();
+            assert_eq!(universe.rgba(), &expected(&universe)[..]);
+        }
+        assert_eq!(universe.rgba_len(), universe.cells_len());
+
+        // Edits show after render, and display modes apply at once
+        universe.set_cell(1, 1, 0, 255, 128, 9);
+        universe.render();
+        assert_eq!(&universe.rgba()[(70 + 1) * 4..(70 + 2) * 4], &[255, 1, 1, 255]);
+        universe.set_display_mode(true, true);
+        assert_eq!(&universe.rgba()[(70 + 1) * 4..(70 + 2) * 4], &[0, 255, 128, 9]);
+
+        universe.set_rgba_enabled(false);
+        assert_eq!(universe.rgba_len(), 0);
+    }
+
+    #[test]
+    fn test_palettes() {
+        for name in PALETTE_NAMES {
+            let palette = Palette::named(name).unwrap();
+            assert_eq!(palette.colors[0][3], 255);
+        }
+        let viridis = Palette::named("Viridis").unwrap();
+        assert_eq!(viridis.colors[0], [0x44, 0x01, 0x54, 255]);
+        assert_eq!(viridis.colors[255], [0xfd, 0xe7, 0x25, 255]);
+        assert!(Palette::named("nope").is_none());
+
+        // Stops default to 0 and 1 and spread evenly in between
+        let palette = Palette::from_json(r##"["#000", [255, 0, 0], {"at": 1.0, "color": "#ffffff80"}]"##).unwrap();
+        assert_eq!(palette.name, "custom");
+        assert_eq!(palette.colors[0], [0, 0, 0, 255]);
+        assert_eq!(palette.colors[51], [102, 0, 0, 255]);
+        assert_eq!(palette.colors[255], [255, 255, 255, 128]);
+        let named = Palette::from_json(r#"{"name": "fire", "stops": [{"at": 0.25, "color": [9, 9, 9]}]}"#).unwrap();
+        assert_eq!(named.name, "fire");
+        assert!(named.colors.iter().all(|&c| c == [9, 9, 9, 255]));
+        let ramp: Vec<String> = (0..256).map(|i| format!("#{:02x}0000", i)).collect();
+        let ramp = Palette::from_json(&serde_json::to_string(&ramp).unwrap()).unwrap();
+        assert!(ramp.colors.iter().enumerate().all(|(i, c)| c[0] as usize == i));
+
+        for bad in ["[]", r#"["red"]"#, r##"["#12345"]"##, "[[1, 2]]", r##"[{"at": 0.8, "color": "#000"}, {"at": 0.2, "color": "#fff"}]"##] {
+            assert!(Palette::from_json(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(Palette::from_rgba("x", &ramp.to_rgba()).unwrap(), Palette { name: "x".into(), ..ramp });
+        assert!(Palette::from_rgba("x", &[0; 12]).is_err());
+    }
+
+    #[test]
+    fn test_framebuffer_renders_through_palette() {
+        let mut universe = Universe::new(8, 8);
+        universe.set_rgba_enabled(true);
+        universe.set_display_mode(false, false);
+        universe.set_cell(2, 3, 10, 20, 30, 255);
+        universe.set_palette("grayscale").unwrap();
+        assert_eq!(universe.palette_name().as_deref(), Some("grayscale"));
+        assert_eq!(universe.palette_rgba().len(), 1024);
+        let pixel = |u: &Universe| u.rgba()[(2 * 8 + 3) * 4..(2 * 8 + 4) * 4].to_vec();
+        assert_eq!(pixel(&universe), [255, 255, 255, 255]);
+
+        universe.set_palette_channel(LifeChannel::Luminance);
+        assert_eq!(pixel(&universe), [30, 30, 30, 255]);
+        universe.set_display_mode(true, false);
+        universe.set_cell(2, 3, 10, 20, 30, 51);
+        universe.render();
+        assert_eq!(pixel(&universe), [30, 30, 30, 51]);
+
+        assert!(universe.set_palette("nope").is_err());
+        assert!(universe.set_palette_json("{").is_err());
+        assert_eq!(universe.palette_name().as_deref(), Some("grayscale"));
+
+        universe.clear_palette();
+        assert_eq!(universe.palette_name(), None);
+        assert!(universe.palette_rgba().is_empty());
+        assert_eq!(pixel(&universe), universe.cells()[2 * 8 + 3].to_rgba(true, false));
+        assert_eq!(Universe::palette_names().len(), PALETTE_NAMES.len());
+    }
+
+    #[test]
+    fn test_age_tracking() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+        universe.set_binary_mode(true);
+        assert!(universe.ages().is_empty());
+        universe.set_age_tracking(true);
+        assert_eq!(universe.ages_len(), 256);
+        // A block and a blinker; the toggles count as births at the next tick
+        for (row, col) in [(2, 2), (2, 3), (3, 2), (3, 3), (8, 6), (8, 7), (8, 8)] {
+            universe.toggle(row, col);
+        }
+        universe.tick_n(4);
+        let age = |u: &Universe, row: usize, col: usize| u.ages()[row * 16 + col];
+        assert_eq!(age(&universe, 2, 2), 3);
+        assert_eq!(age(&universe, 8, 7), 3);
+        assert_eq!(age(&universe, 8, 6), 0);
+        assert_eq!(age(&universe, 7, 7), 0);
+        assert_eq!(age(&universe, 0, 0), 4);
+        let stats = universe.stats();
+        assert_eq!(stats.max_age(), 3);
+        assert!((stats.mean_age() - 15.0 / 7.0).abs() < 1e-5);
+
+        // Heat map through the default palette
+        let inferno = Palette::named("inferno").unwrap();
+        universe.set_rgba_enabled(true);
+        universe.set_display_mode(false, false);
+        universe.set_render_source(RenderSource::Age);
+        universe.set_age_range(4);
+        let pixel = |u: &Universe, row: usize, col: usize| u.rgba()[(row * 16 + col) * 4..][..4].to_vec();
+        assert_eq!(pixel(&universe, 0, 0), inferno.colors[255]);
+        assert_eq!(pixel(&universe, 2, 2), inferno.colors[3 * 255 / 4]);
+        universe.tick();
+        assert_eq!(pixel(&universe, 2, 2), inferno.colors[255]);
+        universe.set_palette("grayscale").unwrap();
+        assert_eq!(pixel(&universe, 8, 7), [255, 255, 255, 255]);
+
+        // Ages travel with saves
+        let mut loaded = Universe::new(4, 4);
+        loaded.load_state(&universe.save_state()).unwrap();
+        assert!(loaded.age_tracking());
+        assert_eq!(loaded.ages(), universe.ages());
+
+        universe.set_age_tracking(false);
+        assert_eq!(universe.ages_len(), 0);
+        assert_eq!(universe.stats().max_age(), 0);
+        universe.randomize();
+        universe.tick();
+        assert_eq!(universe.stats().mean_age(), 0.0);
+    }
+
+    #[test]
+    fn test_activity_tracking() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+        universe.set_binary_mode(true);
+        universe.set_activity_tracking(true);
+        universe.set_activity_window(4);
+        assert_eq!(universe.activity_len(), 256);
+        for (row, col) in [(2, 2), (2, 3), (3, 2), (3, 3), (8, 6), (8, 7), (8, 8)] {
+            universe.toggle(row, col);
+        }
+        universe.tick_n(4);
+
+        // Blinker ends flip every generation; the block and its centre never do
+        let activity = |u: &mut Universe, row: usize, col: usize| u.activity()[row * 16 + col];
+        let flipping = 1.0 - 0.75f32.powi(4);
+        assert!((activity(&mut universe, 8, 6) - flipping).abs() < 1e-6);
+        assert!((activity(&mut universe, 7, 7) - flipping).abs() < 1e-6);
+        assert_eq!(activity(&mut universe, 8, 7), 0.0);
+        assert_eq!(activity(&mut universe, 2, 2), 0.0);
+        assert!((universe.region_activity(6, 7, 3, 3) - 4.0 * flipping / 9.0).abs() < 1e-6);
+        assert_eq!(universe.region_activity(0, 0, 5, 5), 0.0);
+        assert_eq!(universe.region_activity(20, 20, 5, 5), 0.0);
+
+        universe.set_rgba_enabled(true);
+        universe.set_display_mode(false, false);
+        universe.set_render_source(RenderSource::Activity);
+        let inferno = Palette::named("inferno").unwrap();
+        assert_eq!(&universe.rgba()[(8 * 16 + 6) * 4..][..4], &inferno.colors[(flipping * 255.0).round() as usize]);
+        assert_eq!(&universe.rgba()[..4], &inferno.colors[0]);
+
+        let mut loaded = Universe::new(4, 4);
+        loaded.load_state(&universe.save_state()).unwrap();
+        assert!(loaded.activity_tracking());
+        assert_eq!(loaded.activity(), universe.activity());
+
+        universe.set_activity_tracking(false);
+        assert_eq!(universe.activity_len(), 0);
+        universe.tick();
+        assert_eq!(universe.region_activity(0, 0, 16, 16), 0.0);
+    }
+
+    #[test]
+    fn test_activity_decays_lazily() {
+        for binary in [false, true] {
+            let mut universe = Universe::new(80, 40);
+            universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+            universe.set_binary_mode(binary);
+            universe.set_seed(11);
+            universe.randomize();
+            universe.set_activity_tracking(true);
+            universe.set_activity_window(8);
+
+            // Every cell decayed every generation, as the average is defined
+            let mut expected = vec![0.0f32; 80 * 40];
+            let alive = |u: &Universe| -> Vec<bool> {
+                u.cells().iter().map(|c| c.activity_value(LifeChannel::Luminance) > 0).collect()
+            };
+            let mut before = alive(&universe);
+            for generation in 0..60 {
+                if generation == 30 {
+                    universe.set_activity_window(3);
+                }
+                universe.tick();
+                let after = alive(&universe);
+                let weight = 1.0 / universe.activity_window() as f32;
+                for ((value, old), new) in expected.iter_mut().zip(&before).zip(&after) {
+                    *value += weight * ((old != new) as u8 as f32 - *value);
+                }
+                before = after;
+            }
+
+            let region: f32 = expected[..10].iter().sum::<f32>() / 10.0;
+            assert!((universe.region_activity(0, 0, 10, 1) - region).abs() < 1e-5);
+            for (value, expected) in universe.activity().iter().zip(&expected) {
+                assert!((value - expected).abs() < 1e-5, "{} != {}", value, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_trail_accumulation() {
+        let blinker = || {
+            let mut universe = Universe::new(16, 16);
+            universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+            universe.set_binary_mode(true);
+            universe.set_display_mode(false, false);
+            for col in 6..9 {
+                universe.toggle(8, col);
+            }
+            universe
+        };
+        let pixel = |rgba: &[u8], row: usize, col: usize| rgba[(row * 16 + col) * 4..][..4].to_vec();
+
+        let mut universe = blinker();
+        assert!(universe.trail_rgba().is_empty());
+        universe.set_trail_enabled(true);
+        assert_eq!(universe.trail_len(), 16 * 16 * 4);
+        universe.tick_n(4);
+        let trail = universe.trail_rgba();
+        assert_eq!(pixel(&trail, 7, 7), [255, 255, 255, 255]);
+        assert_eq!(pixel(&trail, 8, 6), [255, 255, 255, 255]);
+        assert_eq!(pixel(&trail, 0, 0), [0, 0, 0, 255]);
+
+        // The end cell is alive every other generation, starting with the second
+        let mut average = blinker();
+        average.set_trail_enabled(true);
+        average.set_trail_mode(TrailMode::Average, 0.5);
+        average.tick_n(4);
+        assert_eq!(pixel(&average.trail_rgba(), 8, 6), [170, 170, 170, 239]);
+
+        let mut additive = blinker();
+        additive.set_trail_enabled(true);
+        additive.set_trail_mode(TrailMode::Additive, 1.0);
+        additive.set_trail_exposure(0.125);
+        additive.tick_n(4);
+        assert_eq!(pixel(&additive.trail_rgba(), 8, 7), [255, 255, 255, 128]);
+        assert_eq!(pixel(&additive.trail_rgba(), 8, 6), [128, 128, 128, 128]);
+
+        // Trails leave the simulation alone
+        let mut plain = blinker();
+        plain.tick_n(4);
+        assert_eq!(plain.cells(), universe.cells());
+
+        universe.clear_trail();
+        assert!(universe.trail_rgba().iter().all(|&b| b == 0));
+        universe.set_trail_enabled(false);
+        assert_eq!(universe.trail_len(), 0);
+    }
+
+    #[test]
+    fn test_png_export_and_import() {
+        let mut universe = Universe::new(12, 10);
+        universe.set_seed(5);
+        universe.randomize();
+        universe.tick_n(3);
+
+        // Raw HSLA round-trips exactly, at any size by nearest pixel
+        let raw = universe.export_png_raw().unwrap();
+        assert!(RgbaImage::decode_png(&raw).unwrap().raw_hsla);
+        let mut copy = Universe::new(12, 10);
+        copy.import_png(&raw).unwrap();
+        assert_eq!(copy.cells(), universe.cells());
+        let mut double = Universe::new(24, 20);
+        double.import_png(&raw).unwrap();
+        assert_eq!(double.cells()[(2 * 3 + 1) * 24 + 2 * 5], universe.cells()[3 * 12 + 5]);
+
+        // The render matches the framebuffer
+        universe.set_display_mode(false, false);
+        universe.set_palette("viridis").unwrap();
+        universe.render();
+        let image = RgbaImage::decode_png(&universe.export_png().unwrap()).unwrap();
+        assert!(!image.raw_hsla);
+        assert_eq!((image.width, image.height), (12, 10));
+        assert_eq!(image.pixels, universe.rgba());
+
+        // Ordinary images are converted to HSL
+        let pixels = [[255, 0, 0, 255], [0, 0, 255, 40]].concat();
+        let png = RgbaImage::new(2, 1, pixels).encode_png().unwrap();
+        let mut small = Universe::new(2, 1);
+        small.import_png(&png).unwrap();
+        assert_eq!(small.cells()[0], Individual { hue: 0, saturation: 255, luminance: 128, alpha: 255 });
+        assert_eq!(small.cells()[1], Individual::from_rgba(0, 0, 255, 40));
+        assert_eq!(small.cells()[1].hue, 170);
+
+        assert!(small.import_png(b"not a png").is_err());
+        assert!(Universe::new(0, 0).import_png(&png).is_err());
+    }
+
+    #[test]
+    fn test_animation_export() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_params(0x1808, 255, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Alpha);
+        universe.set_binary_mode(true);
+        universe.place_pattern(&glider(), 2, 2);
+        universe.set_display_mode(false, false);
+        let before = universe.cells().clone();
+
+        // Frames at generations 0, 3, 6 and 8
+        let apng = universe.export_animation(&AnimationOptions::new(AnimationFormat::Apng, 8, 3)).unwrap();
+        assert_eq!(universe.cells(), &before);
+        assert_eq!(universe.generation(), 0);
+        let mut reader = png::Decoder::new(&apng[..]).read_info().unwrap();
+        assert_eq!(reader.info().animation_control.unwrap().num_frames, 4);
+        let mut frame = vec![0; reader.output_buffer_size()];
+        for _ in 0..4 {
+            reader.next_frame(&mut frame).unwrap();
+        }
+        universe.tick_n(8);
+        universe.render();
+        assert_eq!(frame, universe.rgba());
+
+        let mut options = AnimationOptions::new(AnimationFormat::Gif, 4, 1);
+        options.set_crop(1, 1, 4, 5);
+        options.scale = 2.0;
+        let gif = universe.export_animation(&options).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(&gif[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (8, 10));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 10);
+            frames += 1;
+        }
+        assert_eq!(frames, 5);
+
+        options.set_crop(10, 10, 8, 8);
+        assert!(universe.export_animation(&options).is_err());
+        options.set_crop(0, 0, 0, 0);
+        options.scale = 0.0;
+        assert!(universe.export_animation(&options).is_err());
+    }
+
+    #[test]
+    fn test_image_import() {
+        // Area and bilinear agree on a flat image and average a checkerboard
+        let grey = RgbaImage::new(4, 4, [128, 128, 128, 255].repeat(16));
+        for resample in [Resample::Area, Resample::Bilinear] {
+            assert_eq!(grey.resize(2, 3, resample).pixels, [128, 128, 128, 255].repeat(6));
+        }
+        let checker: Vec<u8> = (0..16).flat_map(|i| if (i % 4 + i / 4) % 2 == 0 { [0, 0, 0, 255] } else { [255; 4] }).collect();
+        let halved = RgbaImage::new(4, 4, checker).resize(2, 2, Resample::Area);
+        assert!(halved.pixels.chunks_exact(4).all(|p| p == [128, 128, 128, 255]));
+
+        // A 4x2 opaque image into an 8x8 grid
+        let image: Vec<u8> = [255, 0, 0, 255].repeat(8);
+        let png = RgbaImage::new(4, 2, image).encode_png().unwrap();
+        let covered = |placement: Placement| {
+            let mut universe = Universe::new(8, 8);
+            let options = ImportOptions::new(Resample::Nearest, placement, Dither::None);
+            universe.import_image(&png, &options).unwrap();
+            (0..64).filter(|&i| universe.cells()[i].alpha > 0).map(|i| (i / 8, i % 8)).collect::<Vec<_>>()
+        };
+        let fit = covered(Placement::Fit);
+        assert_eq!(fit.len(), 32);
+        assert!(fit.iter().all(|&(row, _)| (2..6).contains(&row)));
+        assert_eq!(covered(Placement::Fill).len(), 64);
+        assert_eq!(covered(Placement::Stretch).len(), 64);
+        assert_eq!(covered(Placement::Tile).len(), 64);
+
+        // Fill scales only the window that shows, matching a full resize
+        let gradient: Vec<u8> = (0..6 * 3).flat_map(|i| [(i * 14) as u8, 255 - (i * 9) as u8, 40, 255]).collect();
+        let gradient = RgbaImage::new(6, 3, gradient);
+        let full = gradient.resize(20, 10, Resample::Bilinear);
+        let mut universe = Universe::new(10, 10);
+        let options = ImportOptions::new(Resample::Bilinear, Placement::Fill, Dither::None);
+        universe.import_rgba(6, 3, &gradient.pixels, &options).unwrap();
+        for (i, cell) in universe.cells().iter().enumerate() {
+            let p = &full.pixels[((i / 10) * 20 + 5 + i % 10) * 4..][..4];
+            assert_eq!(*cell, Individual::from_rgba(p[0], p[1], p[2], p[3]));
+        }
+        let mut universe = Universe::new(500, 500);
+        universe.import_rgba(1, 500, &[200; 2000], &options).unwrap();
+        assert!(universe.cells().iter().all(|c| c.alpha == 200));
+
+        // A region clips to the grid; one off the grid imports nothing
+        let mut universe = Universe::new(8, 8);
+        let mut options = ImportOptions::new(Resample::Nearest, Placement::Stretch, Dither::None);
+        options.set_region(6, 6, 4, 4);
+        universe.import_image(&png, &options).unwrap();
+        assert_eq!(universe.cells().iter().filter(|c| c.alpha > 0).count(), 4);
+        options.set_region(8, 0, 4, 4);
+        universe.import_image(&png, &options).unwrap();
+        assert_eq!(universe.cells().iter().filter(|c| c.alpha > 0).count(), 4);
+        assert!(universe.import_rgba(2, 2, &[0; 15], &options).is_err());
+
+        // Dithering mid-grey seeds about half the cells, as one undoable edit
+        let grey = [128, 128, 128, 255].repeat(32 * 32);
+        for dither in [Dither::Ordered, Dither::FloydSteinberg] {
+            let mut universe = Universe::new(32, 32);
+            universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+            let options = ImportOptions::new(Resample::Area, Placement::Stretch, dither);
+            universe.import_rgba(32, 32, &grey, &options).unwrap();
+            let live = universe.cells().iter().filter(|c| c.luminance == 255).count();
+            let dead = universe.cells().iter().filter(|c| c.luminance == 0).count();
+            assert_eq!(live + dead, 1024);
+            assert!((480..=544).contains(&live), "{:?}: {} live", dither, live);
+            assert!(universe.undo());
+            assert!(universe.cells().iter().all(|c| c.alpha == 0));
+        }
+    }
+
+    #[test]
+    fn test_stamp_transforms_and_blends() {
+        // A 3x1 stamp: red, green, blue
+        let stamp = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255];
+        let red = Individual::from_rgba(255, 0, 0, 255);
+        let blue = Individual::from_rgba(0, 0, 255, 255);
+        let at = |universe: &Universe, row: u32, col: u32| universe.cells()[(row * universe.width() + col) as usize];
+        let painted = |universe: &Universe| universe.cells().iter().filter(|c| c.alpha > 0).count();
+
+        let mut universe = Universe::new(8, 8);
+        universe.draw_stamp_at(4, 4, 3, 1, &stamp);
+        assert_eq!((at(&universe, 4, 3), at(&universe, 4, 5)), (red, blue));
+
+        // Rotated a quarter turn clockwise, the row becomes a column, top red
+        let mut options = StampOptions::new(BlendMode::Replace);
+        options.rotation = 90.0;
+        let mut universe = Universe::new(8, 8);
+        universe.draw_stamp(4, 4, 3, 1, &stamp, &options);
+        assert_eq!(painted(&universe), 3);
+        assert_eq!((at(&universe, 3, 4), at(&universe, 5, 4)), (red, blue));
+
+        options.rotation = 0.0;
+        options.set_flip(true, false);
+        let mut universe = Universe::new(8, 8);
+        universe.draw_stamp(4, 4, 3, 1, &stamp, &options);
+        assert_eq!((at(&universe, 4, 3), at(&universe, 4, 5)), (blue, red));
+
+        options.set_flip(false, false);
+        options.scale = 2.0;
+        let mut universe = Universe::new(8, 8);
+        universe.draw_stamp(4, 4, 3, 1, &stamp, &options);
+        assert_eq!(painted(&universe), 12);
+        options.scale = 0.0;
+        universe.draw_stamp(4, 4, 3, 1, &stamp, &options);
+        universe.draw_stamp(4, 4, 3, 2, &stamp, &StampOptions::default());
+        assert_eq!(painted(&universe), 12);
+
+        // Off the corner, clipped or wrapped round the torus
+        options.scale = 1.0;
+        let mut universe = Universe::new(8, 8);
+        universe.draw_stamp(0, 0, 3, 1, &stamp, &options);
+        assert_eq!(painted(&universe), 2);
+        options.wrap = true;
+        universe.draw_stamp(0, 0, 3, 1, &stamp, &options);
+        assert_eq!(at(&universe, 0, 7), red);
+        assert!(universe.undo());
+        assert_eq!(painted(&universe), 2);
+
+        // Blend modes over a mid-grey, half-transparent cell
+        let mut universe = Universe::new(4, 4);
+        universe.set_params(0x1808, 1, 60, 0.8, 0.6, 0.95, 0.95, 0.9, 0.01, 0.1, LifeChannel::Luminance);
+        let blend = |universe: &mut Universe, mode: BlendMode, pixel: [u8; 4]| {
+            universe.set_cell(1, 1, 100, 100, 100, 128);
+            universe.draw_stamp(1, 1, 1, 1, &pixel, &StampOptions::new(mode));
+            at(universe, 1, 1)
+        };
+        let white = [255, 255, 255, 255];
+        let cell = |hue, saturation, luminance, alpha| Individual { hue, saturation, luminance, alpha };
+        assert_eq!(blend(&mut universe, BlendMode::Replace, [255, 255, 255, 64]), cell(0, 0, 255, 64));
+        assert_eq!(blend(&mut universe, BlendMode::Lerp, white), cell(0, 0, 255, 255));
+        assert_eq!(blend(&mut universe, BlendMode::Lerp, [255, 255, 255, 128]), cell(50, 50, 178, 255));
+        assert_eq!(blend(&mut universe, BlendMode::Add, white), cell(100, 100, 255, 255));
+        assert_eq!(blend(&mut universe, BlendMode::Add, [0, 0, 0, 128]), cell(100, 100, 100, 192));
+        assert_eq!(blend(&mut universe, BlendMode::Multiply, [128, 128, 128, 255]), cell(100, 0, 50, 128));
+        assert_eq!(blend(&mut universe, BlendMode::Hue, [0, 0, 255, 255]), cell(170, 100, 100, 128));
+        assert_eq!(blend(&mut universe, BlendMode::Luminance, white), cell(100, 100, 255, 128));
+        assert_eq!(blend(&mut universe, BlendMode::LifeChannel, [0, 0, 0, 255]), cell(100, 100, 0, 128));
+        assert_eq!(blend(&mut universe, BlendMode::Max, [0, 0, 0, 255]), cell(100, 100, 100, 255));
+    }
+
+    #[test]
+    fn test_brush_settings() {
+        let paint = |settings: &BrushSettings| {
+            let mut universe = Universe::new(32, 32);
+            universe.draw_brush_with(16, 16, true, 100, 150, 200, 1, settings);
+            universe
+        };
+        let painted = |universe: &Universe| universe.cells().iter().filter(|c| c.alpha > 0).count();
+
+        assert_eq!(painted(&paint(&BrushSettings::new(BrushShape::Circle, 2))), 13);
+        assert_eq!(painted(&paint(&BrushSettings::new(BrushShape::Square, 2))), 25);
+        assert_eq!(painted(&paint(&BrushSettings::new(BrushShape::Diamond, 2))), 13);
+        assert_eq!(painted(&paint(&BrushSettings::new(BrushShape::Ring, 3))), 16);
+        let mut custom = BrushSettings::new(BrushShape::Circle, 1);
+        assert!(custom.set_custom(2, 2, &[255, 0, 0]).is_err());
+        assert_eq!(
+            custom.set_custom(70000, 70000, &[]),
+            Err("a 70000x70000 brush mask needs 4900000000 bytes, got 0".to_string())
+        );
+        custom.set_custom(2, 2, &[255, 0, 0, 255]).unwrap();
+        assert_eq!(custom.shape, BrushShape::Custom);
+        assert_eq!(painted(&paint(&custom)), 5);
+
+        // Soft edges mix the brush colour into what is there
+        let mut soft = BrushSettings::new(BrushShape::Circle, 2);
+        soft.softness = 1.0;
+        let universe = paint(&soft);
+        assert_eq!(universe.cells()[16 * 32 + 16].luminance, 200);
+        assert_eq!(universe.cells()[16 * 32 + 17].luminance, 100);
+        assert_eq!(universe.cells()[16 * 32 + 18].alpha, 0);
+
+        // Spray paints about its density, the same way each time
+        let mut spray = BrushSettings::new(BrushShape::Square, 10);
+        spray.density = 0.5;
+        let sprayed = paint(&spray);
+        assert!((170..=270).contains(&painted(&sprayed)));
+        assert_eq!(paint(&spray).cells(), sprayed.cells());
+
+        let mut jitter = BrushSettings::new(BrushShape::Square, 3);
+        jitter.hue_jitter = 10;
+        let universe = paint(&jitter);
+        let hues: Vec<u8> = universe.cells().iter().filter(|c| c.alpha > 0).map(|c| c.hue).collect();
+        assert!(hues.iter().all(|h| (90..=110).contains(h)));
+        assert!(hues.iter().any(|&h| h != hues[0]));
+
+        // A stroke of spaced dabs is painted as one unbroken line from its
+        // second point to its second-to-last, as the spline trails the brush
+        let mut universe = Universe::new(48, 16);
+        for x in (2..46).step_by(4) {
+            universe.draw_brush(x, 8, 0, true, 100, 150, 200, 9);
+        }
+        assert!((6..=38).all(|col| universe.cells()[8 * 48 + col].alpha == 255));
+        assert!(universe.cells().iter().enumerate().all(|(i, c)| c.alpha == 0 || i / 48 == 8));
+
+        // Settings carry through the spline stroke, undone as one edit
+        let mut universe = Universe::new(32, 32);
+        for (x, y) in [(4, 4), (8, 6), (12, 8), (16, 10), (20, 12)] {
+            universe.draw_brush_with(x, y, true, 100, 150, 200, 7, &spray);
+        }
+        assert!(painted(&universe) > painted(&sprayed));
+        assert!(universe.undo());
+        assert_eq!(painted(&universe), 0);
+    }
+}
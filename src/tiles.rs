@@ -0,0 +1,205 @@
+use crate::{Individual, LifeChannel};
+
+/// Edge length of the square tiles `tick` tracks activity for.
+pub const TILE_SIZE: u32 = 32;
+
+/// How much of a tile's cached statistics is up to date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum StatsLevel {
+    None,
+    /// Sums and live count.
+    Basic,
+    /// Sums, live count and channel histograms.
+    Full,
+}
+
+/// Channel histograms and sums for the cells of one tile.
+#[derive(Clone, Default)]
+pub(crate) struct TileStats {
+    /// Only allocated at `StatsLevel::Full`: at 8 KB a tile it would
+    /// otherwise double the memory of a grid whose stats are off.  `None`
+    /// counts as all zeros.
+    hist: Option<Box<[[u16; 256]; 4]>>,
+    sums: [u32; 4],
+    alive: u32,
+}
+
+impl TileStats {
+    /// Empty statistics, with room for histograms at `StatsLevel::Full`.
+    pub fn new(level: StatsLevel) -> Self {
+        let hist = (level == StatsLevel::Full).then(|| Box::new([[0; 256]; 4]));
+        Self { hist, ..Self::default() }
+    }
+
+    /// Account for `cell`; histograms are only filled at `StatsLevel::Full`.
+    #[inline]
+    pub fn add(&mut self, cell: Individual, channel: LifeChannel) {
+        let values = [cell.hue, cell.saturation, cell.luminance, cell.alpha];
+        for (ch, &v) in values.iter().enumerate() {
+            if let Some(hist) = &mut self.hist {
+                hist[ch][v as usize] += 1;
+            }
+            self.sums[ch] += v as u32;
+        }
+        if cell.activity_value(channel) > 0 {
+            self.alive += 1;
+        }
+    }
+}
+
+/// Dirty-tile tracking for `Universe::tick`.
+///
+/// A tile is recomputed only if one of its cells, or a cell in one of the
+/// eight surrounding tiles, changed during the previous generation.  Skipped
+/// tiles contribute their cached histograms to the universe statistics.
+///
+/// The totals always equal the sum of the cached tile statistics; `valid`
+/// records which of those caches are current so generations that skip
+/// statistics only pay for a rescan of the stale tiles when asked.
+#[derive(Clone)]
+pub(crate) struct ActiveTiles {
+    tiles_x: u32,
+    tiles_y: u32,
+    changed: Vec<bool>,
+    valid: Vec<StatsLevel>,
+    stats: Vec<TileStats>,
+    hist: [[usize; 256]; 4],
+    sums: [u64; 4],
+    alive: usize,
+}
+
+impl ActiveTiles {
+    pub fn new(width: u32, height: u32) -> Self {
+        let tiles_x = width.div_ceil(TILE_SIZE);
+        let tiles_y = height.div_ceil(TILE_SIZE);
+        let count = (tiles_x * tiles_y) as usize;
+        Self {
+            tiles_x,
+            tiles_y,
+            changed: vec![true; count],
+            valid: vec![StatsLevel::None; count],
+            stats: vec![TileStats::default(); count],
+            hist: [[0; 256]; 4],
+            sums: [0; 4],
+            alive: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.changed.len()
+    }
+
+    /// Flag the tile containing `(row, col)` for recomputation.
+    #[inline]
+    pub fn mark(&mut self, row: u32, col: u32) {
+        let tile = (row / TILE_SIZE * self.tiles_x + col / TILE_SIZE) as usize;
+        if let Some(changed) = self.changed.get_mut(tile) {
+            *changed = true;
+        }
+    }
+
+    /// Flag every tile, e.g. after the grid or the rule changed wholesale.
+    pub fn mark_all(&mut self) {
+        self.changed.fill(true);
+    }
+
+    /// Number of tiles that will be recomputed on the next generation.
+    pub fn active_count(&self) -> usize {
+        self.active_set().iter().filter(|&&a| a).count()
+    }
+
+    /// Tiles to recompute: every changed tile and its toroidal neighbours.
+    pub fn active_set(&self) -> Vec<bool> {
+        let (tx, ty) = (self.tiles_x as usize, self.tiles_y as usize);
+        let mut active = vec![false; self.changed.len()];
+        for (tile, _) in self.changed.iter().enumerate().filter(|(_, &c)| c) {
+            let (x, y) = (tile % tx, tile / tx);
+            for dy in [ty - 1, 0, 1] {
+                for dx in [tx - 1, 0, 1] {
+                    active[((y + dy) % ty) * tx + (x + dx) % tx] = true;
+                }
+            }
+        }
+        active
+    }
+
+    /// Cell bounds `(col_start, row_start, col_end, row_end)` of `tile`.
+    pub fn bounds(&self, tile: usize, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let x0 = (tile as u32 % self.tiles_x) * TILE_SIZE;
+        let y0 = (tile as u32 / self.tiles_x) * TILE_SIZE;
+        (x0, y0, (x0 + TILE_SIZE).min(width), (y0 + TILE_SIZE).min(height))
+    }
+
+    /// Flag every tile and drop every cached statistic, for when cells were
+    /// rewritten behind the tiles' back.
+    pub fn invalidate_all(&mut self) {
+        self.changed.fill(true);
+        self.valid.fill(StatsLevel::None);
+    }
+
+    /// Flag the tile containing `(row, col)` as changed and drop its cached
+    /// statistics.
+    pub fn invalidate_cell(&mut self, row: u32, col: u32) {
+        let tile = (row / TILE_SIZE * self.tiles_x + col / TILE_SIZE) as usize;
+        if tile < self.changed.len() {
+            self.invalidate(tile, true);
+        }
+    }
+
+    /// Record whether `tile` changed without refreshing its statistics.
+    pub fn invalidate(&mut self, tile: usize, changed: bool) {
+        self.valid[tile] = StatsLevel::None;
+        self.changed[tile] |= changed;
+    }
+
+    /// Tiles whose cached statistics are below `level`.
+    pub fn stale(&self, level: StatsLevel) -> Vec<usize> {
+        (0..self.valid.len()).filter(|&t| self.valid[t] < level).collect()
+    }
+
+    /// Replace the cached statistics of `tile`, computed at `level`, and
+    /// record whether it changed.
+    pub fn update(&mut self, tile: usize, stats: TileStats, level: StatsLevel, changed: bool) {
+        let old = std::mem::replace(&mut self.stats[tile], stats);
+        let new = &self.stats[tile];
+        for ch in 0..4 {
+            if let Some(hist) = &new.hist {
+                for (total, &n) in self.hist[ch].iter_mut().zip(hist[ch].iter()) {
+                    *total += n as usize;
+                }
+            }
+            if let Some(hist) = &old.hist {
+                for (total, &o) in self.hist[ch].iter_mut().zip(hist[ch].iter()) {
+                    *total -= o as usize;
+                }
+            }
+            self.sums[ch] = self.sums[ch] + new.sums[ch] as u64 - old.sums[ch] as u64;
+        }
+        self.alive = self.alive + new.alive as usize - old.alive as usize;
+        self.valid[tile] = level;
+        self.changed[tile] |= changed;
+    }
+
+    /// Whether `tile` is flagged as changed.
+    pub fn is_changed(&self, tile: usize) -> bool {
+        self.changed[tile]
+    }
+
+    /// Forget which tiles changed; `tick` re-flags them as it goes.
+    pub fn reset_changed(&mut self) {
+        self.changed.fill(false);
+    }
+
+    /// Whole-grid histogram of channel `ch` (hue, saturation, luminance, alpha).
+    pub fn histogram(&self, ch: usize) -> &[usize; 256] {
+        &self.hist[ch]
+    }
+
+    pub fn sum(&self, ch: usize) -> u64 {
+        self.sums[ch]
+    }
+
+    pub fn alive(&self) -> usize {
+        self.alive
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// How each generation is blended into the trail buffer.  `persistence`
+/// fades what was there before the new generation is added.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TrailMode {
+    /// `trail * persistence + frame`; grows past 1 where cells linger, so
+    /// lower the exposure to keep it in range.
+    Additive,
+    /// `max(trail * persistence, frame)`.
+    Max,
+    /// `trail * persistence + frame * (1 - persistence)`, a moving average.
+    Average,
+}
+
+/// Long-exposure accumulation of rendered generations, kept apart from the
+/// cells so it never feeds back into the rules.  Holds alpha-premultiplied
+/// RGBA floats in `0.0..=1.0` per frame.
+pub(crate) struct TrailBuffer {
+    pub enabled: bool,
+    pub mode: TrailMode,
+    pub persistence: f32,
+    /// Scale applied when exporting.
+    pub exposure: f32,
+    pub values: Vec<f32>,
+}
+
+impl Default for TrailBuffer {
+    fn default() -> Self {
+        Self { enabled: false, mode: TrailMode::Max, persistence: 1.0, exposure: 1.0, values: Vec::new() }
+    }
+}
+
+impl TrailBuffer {
+    /// Start from black, or free the buffer while disabled.
+    pub fn clear(&mut self, cells: usize) {
+        self.values.clear();
+        if self.enabled {
+            self.values.resize(cells * 4, 0.0);
+        }
+    }
+
+    /// Blend in one frame of straight-alpha RGBA pixels.
+    pub fn accumulate(&mut self, pixels: impl ExactSizeIterator<Item = [u8; 4]>) {
+        if self.values.len() != pixels.len() * 4 {
+            self.clear(pixels.len());
+        }
+        let (mode, keep) = (self.mode, self.persistence);
+        for (acc, pixel) in self.values.chunks_exact_mut(4).zip(pixels) {
+            let alpha = pixel[3] as f32 / 255.0;
+            let frame = [
+                pixel[0] as f32 / 255.0 * alpha,
+                pixel[1] as f32 / 255.0 * alpha,
+                pixel[2] as f32 / 255.0 * alpha,
+                alpha,
+            ];
+            for (a, f) in acc.iter_mut().zip(frame) {
+                *a = match mode {
+                    TrailMode::Additive => *a * keep + f,
+                    TrailMode::Max => (*a * keep).max(f),
+                    TrailMode::Average => *a * keep + f * (1.0 - keep),
+                };
+            }
+        }
+    }
+
+    /// The trail as straight-alpha RGBA bytes, scaled by `exposure`.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let byte = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        let mut out = Vec::with_capacity(self.values.len());
+        for acc in self.values.chunks_exact(4) {
+            if acc[3] <= 0.0 {
+                out.extend_from_slice(&[0, 0, 0, 0]);
+                continue;
+            }
+            // Colour is the coverage-weighted mean; exposure brightens coverage
+            out.extend_from_slice(&[
+                byte(acc[0] / acc[3]),
+                byte(acc[1] / acc[3]),
+                byte(acc[2] / acc[3]),
+                byte(acc[3] * self.exposure),
+            ]);
+        }
+        out
+    }
+}